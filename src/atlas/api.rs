@@ -0,0 +1,31 @@
+use async_trait::async_trait;
+
+use crate::atlas::error::Result;
+use crate::atlas::user_request::UserRequest;
+use crate::atlas::user_response::UserResponse;
+
+/// The Atlas organization user operations the reconciler relies on.
+///
+/// [`AtlasUserRepository`](crate::atlas::AtlasUserRepository) implements them against the Atlas Admin API v2.
+/// Other implementations can stand in for Atlas, e.g. to assert which operations a reconciliation made.
+#[async_trait]
+pub trait AtlasUserApi: Send + Sync {
+    /// Invites a new user to the Atlas organization
+    async fn invite_atlas_user(&self, org_id: &str, user: &UserRequest<'_>) -> Result<UserResponse>;
+
+    /// Updates the roles and teams of an existing user in the Atlas organization, failing with `AtlasUserNotFound` if
+    /// it does not exist
+    async fn update_atlas_user(&self, org_id: &str, user_id: &str, user: &UserRequest<'_>) -> Result<UserResponse>;
+
+    /// Removes a user from the Atlas organization
+    async fn delete_atlas_user_from_org(&self, org_id: &str, user_id: &str) -> Result<()>;
+
+    /// Gets a user from the Atlas organization by user ID, failing with `AtlasUserNotFound` if it does not exist
+    async fn get_atlas_user(&self, org_id: &str, user_id: &str) -> Result<UserResponse>;
+
+    /// Finds a user in the Atlas organization by username (email), ignoring case
+    async fn find_atlas_user_by_username(&self, org_id: &str, username: &str) -> Result<Option<UserResponse>>;
+
+    /// Verifies that the credentials are accepted by Atlas
+    async fn verify_credentials(&self) -> Result<()>;
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use digest_auth::AuthContext;
+use digest_auth::HttpMethod;
+use digest_auth::WwwAuthenticateHeader;
+use reqwest::header;
+use reqwest::header::HeaderValue;
+use reqwest::Request;
+use reqwest::Response;
+use tracing::debug;
+
+use crate::atlas::error::Result;
+
+/// HTTP Digest authentication with an Atlas programmatic API key.
+///
+/// The first request is sent without credentials and answered by Atlas with a `401` digest challenge.
+/// The challenge is kept and its nonce reused, with an increasing nonce count, for subsequent requests
+/// until Atlas issues a new one.
+pub struct DigestAuth {
+    public_key: Arc<str>,
+    private_key: Arc<str>,
+    challenge: Mutex<Option<WwwAuthenticateHeader>>,
+}
+
+impl DigestAuth {
+    pub fn new(public_key: Arc<str>, private_key: Arc<str>) -> Self {
+        Self {
+            public_key,
+            private_key,
+            challenge: Mutex::new(None),
+        }
+    }
+
+    /// Computes the `Authorization` header for the request from the last received challenge, if any
+    pub fn authorization(&self, request: &Request) -> Result<Option<HeaderValue>> {
+        let mut challenge = self.challenge.lock().expect("digest challenge lock poisoned");
+
+        let Some(challenge) = challenge.as_mut() else {
+            return Ok(None);
+        };
+
+        let url = request.url();
+        let uri = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let context = AuthContext::new_with_method(
+            self.public_key.as_ref(),
+            self.private_key.as_ref(),
+            uri,
+            Option::<&[u8]>::None,
+            HttpMethod::from(request.method().as_str()),
+        );
+
+        let authorization = challenge.respond(&context)?;
+        Ok(Some(HeaderValue::from_str(&authorization.to_header_string())?))
+    }
+
+    /// Stores the digest challenge of a `401` response and returns whether the request should be retried
+    pub fn accept_challenge(&self, response: &Response) -> Result<bool> {
+        let Some(www_authenticate) = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Ok(false);
+        };
+
+        debug!("Received digest challenge from Atlas");
+
+        let challenge = digest_auth::parse(www_authenticate)?;
+        *self.challenge.lock().expect("digest challenge lock poisoned") = Some(challenge);
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,93 @@
+pub mod digest;
+pub mod token;
+
+use std::sync::Arc;
+
+use reqwest::header;
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use reqwest::Request;
+use reqwest::Response;
+
+use crate::atlas::auth::digest::DigestAuth;
+use crate::atlas::auth::token::ClientCredentials;
+use crate::atlas::auth::token::TokenProvider;
+use crate::atlas::error::Result;
+
+/// Credentials for authenticating against the Atlas Admin API
+#[derive(Clone)]
+pub enum Credentials {
+    /// A pre-issued OAuth access token that is used as-is
+    AccessToken(Arc<str>),
+    /// Service account credentials that are exchanged for short-lived access tokens
+    ServiceAccount {
+        client_id: Arc<str>,
+        client_secret: Arc<str>,
+    },
+    /// A programmatic API key pair used with HTTP Digest authentication
+    ApiKey {
+        public_key: Arc<str>,
+        private_key: Arc<str>,
+    },
+}
+
+/// Authentication strategy applied to every request against the Atlas Admin API
+pub enum Authenticator {
+    /// Bearer tokens, either static or obtained via the OAuth2 client credentials flow
+    Bearer(TokenProvider),
+    /// HTTP Digest authentication with a programmatic API key
+    Digest(DigestAuth),
+}
+
+impl Authenticator {
+    /// Creates the authentication strategy matching the given credentials, using `client` to request tokens
+    /// from `token_url`
+    pub fn new(client: Client, token_url: Arc<str>, credentials: Credentials) -> Self {
+        match credentials {
+            Credentials::AccessToken(access_token) => Authenticator::Bearer(TokenProvider::Static(access_token)),
+            Credentials::ServiceAccount {
+                client_id,
+                client_secret,
+            } => Authenticator::Bearer(TokenProvider::ClientCredentials(ClientCredentials::new(
+                client,
+                token_url,
+                client_id,
+                client_secret,
+            ))),
+            Credentials::ApiKey {
+                public_key,
+                private_key,
+            } => Authenticator::Digest(DigestAuth::new(public_key, private_key)),
+        }
+    }
+
+    /// Adds the `Authorization` header to the request, if the strategy can authorize it upfront
+    pub async fn authorize(&self, request: &mut Request) -> Result<()> {
+        let authorization = match self {
+            Authenticator::Bearer(token_provider) => {
+                let token = token_provider.token().await?;
+                Some(HeaderValue::from_str(&format!("Bearer {token}"))?)
+            }
+            Authenticator::Digest(digest_auth) => digest_auth.authorization(request)?,
+        };
+
+        if let Some(mut authorization) = authorization {
+            authorization.set_sensitive(true);
+            request.headers_mut().insert(header::AUTHORIZATION, authorization);
+        }
+
+        Ok(())
+    }
+
+    /// Processes a `401 Unauthorized` response and returns whether the request should be sent again
+    pub async fn handle_unauthorized(&self, response: &Response) -> Result<bool> {
+        match self {
+            Authenticator::Bearer(token_provider) if token_provider.is_refreshable() => {
+                token_provider.invalidate().await;
+                Ok(true)
+            }
+            Authenticator::Bearer(_) => Ok(false),
+            Authenticator::Digest(digest_auth) => digest_auth.accept_challenge(response),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use reqwest::header;
+use reqwest::Client;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::atlas::error::Error;
+use crate::atlas::error::Result;
+
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Provides bearer tokens for requests against the Atlas Admin API
+pub enum TokenProvider {
+    /// A fixed token that cannot be refreshed
+    Static(Arc<str>),
+    /// Tokens obtained via the OAuth2 client credentials flow, cached until shortly before they expire
+    ClientCredentials(ClientCredentials),
+}
+
+impl TokenProvider {
+    /// Returns a valid bearer token, requesting a new one if the cached token is about to expire
+    pub async fn token(&self) -> Result<Arc<str>> {
+        match self {
+            TokenProvider::Static(access_token) => Ok(Arc::clone(access_token)),
+            TokenProvider::ClientCredentials(client_credentials) => client_credentials.token().await,
+        }
+    }
+
+    /// Whether a rejected token can be replaced by requesting a new one
+    pub fn is_refreshable(&self) -> bool {
+        matches!(self, TokenProvider::ClientCredentials(_))
+    }
+
+    /// Drops the cached token so that the next call to [`TokenProvider::token`] requests a new one
+    pub async fn invalidate(&self) {
+        if let TokenProvider::ClientCredentials(client_credentials) = self {
+            client_credentials.invalidate().await;
+        }
+    }
+}
+
+/// OAuth2 client credentials flow for Atlas service accounts
+pub struct ClientCredentials {
+    client: Client,
+    token_url: Arc<str>,
+    client_id: Arc<str>,
+    client_secret: Arc<str>,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl ClientCredentials {
+    pub fn new(client: Client, token_url: Arc<str>, client_id: Arc<str>, client_secret: Arc<str>) -> Self {
+        Self {
+            client,
+            token_url,
+            client_id,
+            client_secret,
+            cached: Mutex::new(None),
+        }
+    }
+
+    async fn token(&self) -> Result<Arc<str>> {
+        // Holding the lock while requesting ensures concurrent reconciles share a single refresh
+        let mut cached = self.cached.lock().await;
+
+        if let Some(token) = cached.as_ref().filter(|token| Instant::now() < token.refresh_at) {
+            return Ok(Arc::clone(&token.access_token));
+        }
+
+        let token = self.request_token().await?;
+        let access_token = Arc::clone(&token.access_token);
+        *cached = Some(token);
+
+        Ok(access_token)
+    }
+
+    async fn invalidate(&self) {
+        *self.cached.lock().await = None;
+    }
+
+    async fn request_token(&self) -> Result<CachedToken> {
+        debug!(client_id = %self.client_id, "Requesting new Atlas access token");
+
+        let response = self
+            .client
+            .post(self.token_url.as_ref())
+            .basic_auth(self.client_id.as_ref(), Some(self.client_secret.as_ref()))
+            .header(header::ACCEPT, "application/json")
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let token: TokenResponse = serde_json::from_slice(&response.bytes().await?)?;
+                let valid_for = Duration::from_secs(token.expires_in).saturating_sub(TOKEN_REFRESH_MARGIN);
+
+                Ok(CachedToken {
+                    access_token: token.access_token,
+                    refresh_at: Instant::now() + valid_for,
+                })
+            }
+            status => {
+                let message = response.text().await?;
+                Err(Error::TokenRequest { status, message })
+            }
+        }
+    }
+}
+
+/// A cached access token together with the point in time it should be refreshed
+struct CachedToken {
+    access_token: Arc<str>,
+    refresh_at: Instant,
+}
+
+/// Response of the Atlas OAuth token endpoint
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: Arc<str>,
+    /// Lifetime of the token in seconds
+    expires_in: u64,
+}
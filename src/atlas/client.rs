@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use reqwest::header;
+use reqwest::Certificate;
+use reqwest::Client;
+use reqwest::Proxy;
+
+use crate::atlas::error::Error;
+use crate::atlas::error::Result;
+use crate::atlas::rate_limit::RateLimiter;
+use crate::atlas::retry::RetryPolicy;
+use crate::config::AtlasApiConfig;
+use crate::metrics::Metrics;
+
+const ATLAS_API_V2_PATH: &str = "/api/atlas/v2";
+const ATLAS_OAUTH_TOKEN_PATH: &str = "/api/oauth/token";
+
+/// HTTP client for the Atlas Admin API, shared by the repositories of all credentials so that they share
+/// connections, retries and the rate limits per organization
+pub struct AtlasClient {
+    pub(crate) http: Client,
+    pub(crate) api_url: String,
+    pub(crate) token_url: Arc<str>,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) rate_limiter: RateLimiter,
+    pub(crate) metrics: Arc<Metrics>,
+}
+
+impl AtlasClient {
+    /// Creates a client for the Atlas deployment, API version and network settings of the given configuration
+    pub fn new(config: &AtlasApiConfig, metrics: Arc<Metrics>) -> Result<Self> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json; charset=utf-8"),
+        );
+        headers.insert(
+            header::ACCEPT,
+            header::HeaderValue::from_str(&format!("application/vnd.atlas.{}+json", config.api_version))?,
+        );
+
+        let mut builder = Client::builder()
+            .default_headers(headers)
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout);
+
+        if let Some(proxy) = config.proxy.as_ref() {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+
+        if let Some(path) = config.ca_bundle.as_ref() {
+            let pem = std::fs::read(path).map_err(|source| Error::CaBundle {
+                path: path.clone(),
+                source,
+            })?;
+            for certificate in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let base_url = config.base_url.as_str().trim_end_matches('/');
+
+        Ok(Self {
+            http: builder.build()?,
+            api_url: format!("{base_url}{ATLAS_API_V2_PATH}"),
+            token_url: Arc::from(format!("{base_url}{ATLAS_OAUTH_TOKEN_PATH}")),
+            retry_policy: RetryPolicy::new(config.retry.clone()),
+            rate_limiter: RateLimiter::new(config.rate_limit.clone()),
+            metrics,
+        })
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+use k8s_openapi::api::core::v1::Secret;
+use kuberator::cache::ProvideApi;
+use kuberator::cache::StaticApiProvider;
+use kuberator::TryResource;
+use tracing::info;
+
+use crate::atlas::api::AtlasUserApi;
+use crate::atlas::auth::Credentials;
+use crate::atlas::client::AtlasClient;
+use crate::atlas::error::Error;
+use crate::atlas::error::Result;
+use crate::atlas::repository::AtlasUserRepository;
+use crate::crd::AtlasUser;
+use crate::crd::SecretReference;
+
+const ACCESS_TOKEN_KEY: &str = "accessToken";
+const CLIENT_ID_KEY: &str = "clientId";
+const CLIENT_SECRET_KEY: &str = "clientSecret";
+const PUBLIC_API_KEY_KEY: &str = "publicApiKey";
+const PRIVATE_API_KEY_KEY: &str = "privateApiKey";
+
+/// Provides the Atlas API to use for an `AtlasUser`.
+///
+/// Resources referencing a connection Secret get a repository built from the credentials in that Secret.
+/// Those repositories are cached per Secret and rebuilt whenever the Secret's resource version changes. They are
+/// evicted once no AtlasUser uses the Secret anymore. All other resources use the operator-wide default repository,
+/// if credentials were configured for it.
+pub struct AtlasConnections {
+    default_repo: Option<Arc<dyn AtlasUserApi>>,
+    secret_api_provider: StaticApiProvider<Secret>,
+    atlas_client: Arc<AtlasClient>,
+    cache: Mutex<ConnectionCache>,
+}
+
+/// Namespace and name of a connection Secret
+type SecretKey = (String, String);
+
+/// Namespace and name of an AtlasUser
+type AtlasUserKey = (String, String);
+
+#[derive(Default)]
+struct ConnectionCache {
+    connections: HashMap<SecretKey, CachedConnection>,
+    /// The connection Secret each AtlasUser used last
+    secrets: HashMap<AtlasUserKey, SecretKey>,
+}
+
+/// A repository built from a connection Secret at a specific resource version
+struct CachedConnection {
+    resource_version: Option<String>,
+    atlas_repo: Arc<dyn AtlasUserApi>,
+}
+
+impl AtlasConnections {
+    pub fn new(
+        default_repo: Option<Arc<dyn AtlasUserApi>>,
+        secret_api_provider: StaticApiProvider<Secret>,
+        atlas_client: Arc<AtlasClient>,
+    ) -> Self {
+        Self {
+            default_repo,
+            secret_api_provider,
+            atlas_client,
+            cache: Mutex::new(ConnectionCache::default()),
+        }
+    }
+
+    /// Verifies the operator-wide default credentials against Atlas, if configured.
+    /// Credentials from connection Secrets are checked when they are first used.
+    pub async fn verify_default_credentials(&self) -> Result<()> {
+        match self.default_repo.as_ref() {
+            Some(atlas_repo) => atlas_repo.verify_credentials().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the repository for the given AtlasUser, (re)building it from its connection Secret if needed
+    pub async fn repository(&self, atlas_user: &AtlasUser) -> Result<Arc<dyn AtlasUserApi>> {
+        let namespace = atlas_user.try_namespace()?;
+        let user = (namespace.clone(), atlas_user.try_name()?.to_string());
+
+        let Some(secret_ref) = atlas_user.spec.connection_secret_ref.as_ref() else {
+            self.cache().release(&user);
+            return self.default_repository();
+        };
+
+        let secret = self.secret_api_provider.get(&namespace)?.get(&secret_ref.name).await?;
+        let resource_version = secret.metadata.resource_version.clone();
+        let key = (namespace, secret_ref.name.clone());
+
+        let mut cache = self.cache();
+        cache.track(user, &key);
+        if let Some(cached) = cache.connections.get(&key) {
+            if cached.resource_version == resource_version {
+                return Ok(Arc::clone(&cached.atlas_repo));
+            }
+        }
+
+        info!(namespace = %key.0, secret = %key.1, "Building Atlas client from connection Secret");
+
+        let credentials = credentials_from_secret(&secret_ref.name, &secret)?;
+        let atlas_repo: Arc<dyn AtlasUserApi> =
+            Arc::new(AtlasUserRepository::new(credentials, Arc::clone(&self.atlas_client)));
+        let connection = CachedConnection {
+            resource_version,
+            atlas_repo: Arc::clone(&atlas_repo),
+        };
+        cache.connections.insert(key, connection);
+
+        Ok(atlas_repo)
+    }
+
+    /// Returns the repository for the given connection Secret of the AtlasUser, e.g. the one a previous identity
+    /// was applied with. Repositories of Secrets other than the spec's are built for the caller only, so that they
+    /// are not cached for an AtlasUser that no longer references them.
+    pub async fn repository_for(
+        &self,
+        atlas_user: &AtlasUser,
+        secret_ref: Option<&SecretReference>,
+    ) -> Result<Arc<dyn AtlasUserApi>> {
+        if secret_ref == atlas_user.spec.connection_secret_ref.as_ref() {
+            return self.repository(atlas_user).await;
+        }
+        let Some(secret_ref) = secret_ref else {
+            return self.default_repository();
+        };
+
+        let namespace = atlas_user.try_namespace()?;
+        let secret = self.secret_api_provider.get(&namespace)?.get(&secret_ref.name).await?;
+        let credentials = credentials_from_secret(&secret_ref.name, &secret)?;
+        Ok(Arc::new(AtlasUserRepository::new(
+            credentials,
+            Arc::clone(&self.atlas_client),
+        )))
+    }
+
+    fn default_repository(&self) -> Result<Arc<dyn AtlasUserApi>> {
+        self.default_repo
+            .as_ref()
+            .map(Arc::clone)
+            .ok_or(Error::MissingCredentials)
+    }
+
+    /// Forgets that the AtlasUser uses its connection Secret, once it is no longer managed. The cached repository
+    /// of the Secret is evicted unless other AtlasUsers still use it.
+    pub fn release(&self, atlas_user: &AtlasUser) {
+        let user = (
+            atlas_user.try_namespace().unwrap_or_default(),
+            atlas_user.try_name().unwrap_or_default().to_string(),
+        );
+        self.cache().release(&user);
+    }
+
+    fn cache(&self) -> MutexGuard<'_, ConnectionCache> {
+        self.cache.lock().expect("connection cache lock poisoned")
+    }
+}
+
+impl ConnectionCache {
+    /// Records that the AtlasUser uses the connection Secret, evicting the Secret it used before if unused now
+    fn track(&mut self, user: AtlasUserKey, secret: &SecretKey) {
+        match self.secrets.insert(user, secret.clone()) {
+            Some(previous) if previous != *secret => self.evict_unused(&previous),
+            _ => {}
+        }
+    }
+
+    /// Records that the AtlasUser uses no connection Secret, evicting the Secret it used before if unused now
+    fn release(&mut self, user: &AtlasUserKey) {
+        if let Some(previous) = self.secrets.remove(user) {
+            self.evict_unused(&previous);
+        }
+    }
+
+    fn evict_unused(&mut self, secret: &SecretKey) {
+        if !self.secrets.values().any(|used| used == secret) && self.connections.remove(secret).is_some() {
+            info!(namespace = %secret.0, secret = %secret.1, "Evicted Atlas client of unused connection Secret");
+        }
+    }
+}
+
+/// Reads Atlas credentials from a connection Secret: either `accessToken`, `clientId` and `clientSecret`
+/// of a service account, or `publicApiKey` and `privateApiKey` of a programmatic API key
+fn credentials_from_secret(name: &str, secret: &Secret) -> Result<Credentials> {
+    let value = |key: &str| -> Result<Option<Arc<str>>> {
+        let Some(bytes) = secret.data.as_ref().and_then(|data| data.get(key)) else {
+            return Ok(None);
+        };
+
+        let value = String::from_utf8(bytes.0.clone()).map_err(|_| Error::InvalidConnectionSecret {
+            name: name.to_string(),
+            reason: format!("key {key} is not valid UTF-8"),
+        })?;
+        Ok(Some(Arc::from(value.trim())))
+    };
+
+    match (
+        value(ACCESS_TOKEN_KEY)?,
+        value(CLIENT_ID_KEY)?,
+        value(CLIENT_SECRET_KEY)?,
+        value(PUBLIC_API_KEY_KEY)?,
+        value(PRIVATE_API_KEY_KEY)?,
+    ) {
+        (Some(access_token), None, None, None, None) => Ok(Credentials::AccessToken(access_token)),
+        (None, Some(client_id), Some(client_secret), None, None) => Ok(Credentials::ServiceAccount {
+            client_id,
+            client_secret,
+        }),
+        (None, None, None, Some(public_key), Some(private_key)) => Ok(Credentials::ApiKey {
+            public_key,
+            private_key,
+        }),
+        _ => Err(Error::InvalidConnectionSecret {
+            name: name.to_string(),
+            reason: format!(
+                "expected exactly one of {ACCESS_TOKEN_KEY}, {CLIENT_ID_KEY} and {CLIENT_SECRET_KEY}, \
+                or {PUBLIC_API_KEY_KEY} and {PRIVATE_API_KEY_KEY}"
+            ),
+        }),
+    }
+}
@@ -1,43 +1,93 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
+use std::time::Instant;
 
 use async_trait::async_trait;
+use chrono::Utc;
 use kube::runtime::controller::Action;
+use kube::ResourceExt;
 use kuberator::cache::StaticApiProvider;
 use kuberator::error::Error as KubeError;
 use kuberator::error::Result as KubeResult;
+use kuberator::events::EmitEvent;
+use kuberator::events::EventData;
 use kuberator::k8s::K8sRepository;
 use kuberator::Context;
 use kuberator::Finalize;
 use kuberator::ObserveGeneration;
 use kuberator::TryResource;
+use serde::Serialize;
 use tracing::info;
 use tracing::warn;
 
+use crate::atlas::api::AtlasUserApi;
+use crate::atlas::connection::AtlasConnections;
+use crate::atlas::drift;
 use crate::atlas::error::Error;
-use crate::atlas::repository::AtlasUserRepository;
+use crate::atlas::error::ErrorClass;
+use crate::atlas::events::AtlasUserEventReason;
 use crate::atlas::user_request::UserRequest;
+use crate::atlas::user_response::UserResponse;
 use crate::config::AtlasUserConfig;
+use crate::config::DriftPolicy;
+use crate::config::IdentityChangePolicy;
+use crate::crd::AdoptionPolicy;
+use crate::crd::AppliedIdentity;
 use crate::crd::AtlasUser;
 use crate::crd::AtlasUserStatus;
+use crate::crd::Condition;
+use crate::crd::ConditionStatus;
+use crate::crd::ConditionType;
+use crate::crd::DeletionPolicy;
+use crate::crd::ExternalDeletionPolicy;
+use crate::crd::PreviousMembership;
 use crate::crd::UserOrgMembershipStatus;
+use crate::crd::UserOrigin;
+use crate::crd::DELETION_POLICY_ANNOTATION;
+use crate::index::AtlasUserIndex;
+use crate::index::Owner;
+use crate::k8s::AtlasUserEventRecorder;
 use crate::k8s::AtlasUserK8sRepo;
+use crate::metrics::Metrics;
 
 const FINALIZER: &str = "atlasusers.moertel.com/finalizer";
+/// Delay before the first retry of a reconciliation that failed transiently, doubled for every further failure
+const TRANSIENT_BACKOFF: Duration = Duration::from_secs(5);
+/// Delay before the first retry of a reconciliation that Atlas rate limited beyond the request retries,
+/// doubled for every further failure
+const RATE_LIMITED_BACKOFF: Duration = Duration::from_secs(30);
 
 /// Context for reconciling AtlasUser resources
 pub struct AtlasUserContext {
-    atlas_repo: Arc<AtlasUserRepository>,
+    atlas_connections: Arc<AtlasConnections>,
     k8s_repo: Arc<AtlasUserK8sRepo>,
+    event_recorder: Arc<AtlasUserEventRecorder>,
+    metrics: Arc<Metrics>,
     config: AtlasUserConfig,
+    index: Arc<AtlasUserIndex>,
+    /// Number of consecutive failed reconciliations per resource, driving the backoff
+    failures: Mutex<HashMap<String, u32>>,
 }
 
 impl AtlasUserContext {
-    pub fn new(atlas_repo: Arc<AtlasUserRepository>, k8s_repo: Arc<AtlasUserK8sRepo>, config: AtlasUserConfig) -> Self {
+    pub fn new(
+        atlas_connections: Arc<AtlasConnections>,
+        k8s_repo: Arc<AtlasUserK8sRepo>,
+        event_recorder: Arc<AtlasUserEventRecorder>,
+        metrics: Arc<Metrics>,
+        config: AtlasUserConfig,
+        index: Arc<AtlasUserIndex>,
+    ) -> Self {
         Self {
-            atlas_repo,
+            atlas_connections,
             k8s_repo,
+            event_recorder,
+            metrics,
             config,
+            index,
+            failures: Mutex::new(HashMap::new()),
         }
     }
 
@@ -53,96 +103,634 @@ impl AtlasUserContext {
     }
 
     /// Invites a new user to Atlas
-    async fn invite_user(&self, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
+    async fn invite_user(&self, atlas_repo: &dyn AtlasUserApi, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
         let (name, namespace) = (atlas_user.try_name()?, atlas_user.try_namespace()?);
         let spec = &atlas_user.spec;
 
         info!(name = %name, namespace = %namespace, username = %spec.username, "Inviting new user to Atlas");
 
         let request = UserRequest::for_invite(spec);
-        let response = self.atlas_repo.invite_atlas_user(&spec.org_id, &request).await?;
+        let response = match atlas_repo.invite_atlas_user(&spec.org_id, &request).await {
+            Ok(response) => response,
+            Err(Error::UserAlreadyInOrg(error)) => {
+                info!(name = %name, namespace = %namespace, "User is already a member of the organization, adopting");
+                return match atlas_repo
+                    .find_atlas_user_by_username(&spec.org_id, &spec.username)
+                    .await?
+                {
+                    Some(response) => self.adopt_user(atlas_user, response).await,
+                    None => Err(Error::UserAlreadyInOrg(error).into()),
+                };
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let message = format!("Invited {} to organization {}", spec.username, spec.org_id);
+        let event = EventData::normal(AtlasUserEventReason::Invited, message);
+        self.event_recorder.emit(atlas_user.as_ref(), event).await;
 
         // Update status with the new user ID and membership status
+        let generation = atlas_user.metadata.generation;
         let mut status = atlas_user.status.clone().unwrap_or_default();
+        status.reinvitations = 0;
+        status.deleted_externally_at = None;
+        set_invitation(&mut status, &response);
         status.user_id = Some(response.id);
-        status.membership_status = Some(response.org_membership_status);
-        status.error = None;
+        status.origin = Some(UserOrigin::Created);
+        status.applied_identity = Some(AppliedIdentity::of(spec));
+        set_membership(&mut status, response.org_membership_status, generation);
+        set_synced(
+            &mut status,
+            "UserInvited",
+            "User was invited to the organization",
+            generation,
+        );
         status.with_observed_gen(&atlas_user.metadata);
 
-        self.k8s_repo.update_status(&atlas_user, status).await?;
+        self.update_status(&atlas_user, status).await?;
 
         Ok(Action::requeue(self.config.requeue_duration))
     }
 
+    /// Takes over a user that already exists in the organization by recording its ID in the status, if the
+    /// adoption policy allows it
+    async fn adopt_user(&self, atlas_user: Arc<AtlasUser>, response: UserResponse) -> KubeResult<Action> {
+        self.check_adoption(&atlas_user, &response)?;
+
+        let message = format!(
+            "Adopted existing user {} of organization {}",
+            response.id, atlas_user.spec.org_id
+        );
+        let event = EventData::normal(AtlasUserEventReason::Adopted, message);
+        self.event_recorder.emit(atlas_user.as_ref(), event).await;
+
+        let generation = atlas_user.metadata.generation;
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        status.user_id = Some(Arc::clone(&response.id));
+        status.origin = Some(UserOrigin::Adopted);
+        status.applied_identity = Some(AppliedIdentity::of(&atlas_user.spec));
+        status.deleted_externally_at = None;
+        set_invitation(&mut status, &response);
+        set_membership(&mut status, response.org_membership_status, generation);
+        set_synced(
+            &mut status,
+            "UserFound",
+            "User was found in the organization",
+            generation,
+        );
+        status.with_observed_gen(&atlas_user.metadata);
+
+        self.update_status(&atlas_user, status).await?;
+
+        Ok(Action::requeue(Duration::from_secs(1))) // Requeue immediately to process with user_id
+    }
+
+    /// Fails unless the adoption policy allows taking over the existing user
+    fn check_adoption(&self, atlas_user: &AtlasUser, response: &UserResponse) -> Result<(), Error> {
+        let spec = &atlas_user.spec;
+        let reason = match spec.adoption_policy.unwrap_or(self.config.adoption_policy) {
+            AdoptionPolicy::Adopt => return Ok(()),
+            AdoptionPolicy::AdoptIfRolesMatch => {
+                let drift = drift::detect(spec, response);
+                if drift.is_empty() {
+                    return Ok(());
+                }
+                format!("fields differing from the spec: {}", drift.join(", "))
+            }
+            AdoptionPolicy::Never => "the adoption policy is Never".to_string(),
+        };
+
+        Err(Error::AdoptionRefused {
+            username: spec.username.clone(),
+            org_id: spec.org_id.clone(),
+            reason,
+        })
+    }
+
     /// Updates an existing user in Atlas
-    async fn update_user(&self, atlas_user: Arc<AtlasUser>, user_id: &str) -> KubeResult<Action> {
+    async fn update_user(
+        &self,
+        atlas_repo: &dyn AtlasUserApi,
+        atlas_user: Arc<AtlasUser>,
+        user_id: &str,
+    ) -> KubeResult<Action> {
         let (name, namespace) = (atlas_user.try_name()?.to_string(), atlas_user.try_namespace()?);
         let spec = &atlas_user.spec;
 
         info!(name = %name, namespace = %namespace, user_id = %user_id, "Updating user in Atlas");
 
         let request = UserRequest::for_update(spec);
-        let response = self
-            .atlas_repo
-            .update_atlas_user(&spec.org_id, user_id, &request)
-            .await?;
+        let response = match atlas_repo.update_atlas_user(&spec.org_id, user_id, &request).await {
+            Ok(response) => response,
+            Err(Error::AtlasUserNotFound { .. }) => {
+                warn!(name = %name, namespace = %namespace, "User not found in Atlas, clearing status");
+                return self.handle_external_deletion(atlas_repo, atlas_user).await;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let message = format!("Applied roles and teams to user {}", user_id);
+        let event = EventData::normal(AtlasUserEventReason::Updated, message);
+        self.event_recorder.emit(atlas_user.as_ref(), event).await;
 
         // Update status
+        let generation = atlas_user.metadata.generation;
         let mut status = atlas_user.status.clone().unwrap_or_default();
-        status.membership_status = Some(response.org_membership_status);
-        status.error = None;
+        status.applied_identity = Some(AppliedIdentity::of(spec));
+        set_invitation(&mut status, &response);
+        set_membership(&mut status, response.org_membership_status, generation);
+        set_synced(
+            &mut status,
+            "UserUpdated",
+            "Roles and teams were applied to Atlas",
+            generation,
+        );
+        status.drift = None;
+        status.set_condition(
+            ConditionType::Drifted,
+            false,
+            "SpecApplied",
+            "Roles and teams were applied to Atlas",
+            generation,
+        );
         status.with_observed_gen(&atlas_user.metadata);
 
-        self.k8s_repo.update_status(&atlas_user, status).await?;
+        self.update_status(&atlas_user, status).await?;
 
         Ok(Action::requeue(self.config.requeue_duration))
     }
 
     /// Syncs the status from Atlas to the K8s resource
-    async fn sync_status(&self, atlas_user: Arc<AtlasUser>, user_id: &str) -> KubeResult<Action> {
+    async fn sync_status(
+        &self,
+        atlas_repo: &dyn AtlasUserApi,
+        atlas_user: Arc<AtlasUser>,
+        user_id: &str,
+    ) -> KubeResult<Action> {
         let (name, namespace) = (atlas_user.try_name()?.to_string(), atlas_user.try_namespace()?);
         let spec = &atlas_user.spec;
 
         info!(name = %name, namespace = %namespace, "Syncing user status from Atlas");
 
-        match self.atlas_repo.get_atlas_user(&spec.org_id, user_id).await {
+        match atlas_repo.get_atlas_user(&spec.org_id, user_id).await {
+            Ok(response) if invitation_expired(&response) => {
+                return self.reinvite_user(atlas_repo, atlas_user, user_id, response).await;
+            }
             Ok(response) => {
+                let generation = atlas_user.metadata.generation;
+                let drift = drift::detect(spec, &response);
                 let mut status = atlas_user.status.clone().unwrap_or_default();
-                status.membership_status = Some(response.org_membership_status);
-                status.error = None;
+                status.applied_identity = Some(AppliedIdentity::of(spec));
+                set_invitation(&mut status, &response);
+                set_membership(&mut status, response.org_membership_status, generation);
+                set_synced(
+                    &mut status,
+                    "UserSynced",
+                    "User status was synced from Atlas",
+                    generation,
+                );
+                status.drift = None;
+                status.set_condition(
+                    ConditionType::Drifted,
+                    false,
+                    "InSync",
+                    "Roles and teams in Atlas match the spec",
+                    generation,
+                );
 
-                self.k8s_repo.update_status(&atlas_user, status).await?;
+                if !drift.is_empty() {
+                    warn!(name = %name, namespace = %namespace, drift = ?drift, "User in Atlas drifted from spec");
+                    let message = format!("Fields differing from the spec in Atlas: {}", drift.join(", "));
+
+                    match self.config.drift_policy {
+                        DriftPolicy::Correct => {
+                            info!(name = %name, namespace = %namespace, user_id = %user_id, "Correcting drift in Atlas");
+                            let request = UserRequest::for_update(spec);
+                            let response = atlas_repo.update_atlas_user(&spec.org_id, user_id, &request).await?;
+                            set_membership(&mut status, response.org_membership_status, generation);
+
+                            let event =
+                                EventData::normal(AtlasUserEventReason::Updated, format!("Corrected drift. {message}"));
+                            self.event_recorder.emit(atlas_user.as_ref(), event).await;
+
+                            status.set_condition(ConditionType::Drifted, true, "DriftCorrected", message, generation);
+                        }
+                        DriftPolicy::ReportOnly => {
+                            status.set_condition(
+                                ConditionType::Drifted,
+                                true,
+                                "DriftDetected",
+                                message.as_str(),
+                                generation,
+                            );
+                            status.set_condition(ConditionType::Ready, false, "DriftDetected", message, generation);
+                        }
+                    }
+
+                    status.drift = Some(drift);
+                }
+
+                self.update_status(&atlas_user, status).await?;
             }
             Err(Error::AtlasUserNotFound { .. }) => {
-                // User was deleted externally, clear the user_id
                 warn!(name = %name, namespace = %namespace, "User not found in Atlas, clearing status");
-                let status = AtlasUserStatus {
-                    error: Some("User was deleted externally from Atlas".to_string()),
-                    ..Default::default()
-                };
-
-                self.k8s_repo.update_status(&atlas_user, status).await?;
+                return self.handle_external_deletion(atlas_repo, atlas_user).await;
             }
             Err(e) => return Err(e.into()),
         }
 
         Ok(Action::requeue(self.config.requeue_duration))
     }
-}
 
-#[async_trait]
-impl Context<AtlasUser, AtlasUserK8sRepo, StaticApiProvider<AtlasUser>> for AtlasUserContext {
-    fn k8s_repository(&self) -> Arc<K8sRepository<AtlasUser, StaticApiProvider<AtlasUser>>> {
-        Arc::clone(&self.k8s_repo)
+    /// The policy for a user of the AtlasUser being removed from the organization outside the operator
+    fn external_deletion_policy(&self, atlas_user: &AtlasUser) -> ExternalDeletionPolicy {
+        atlas_user
+            .spec
+            .on_external_deletion
+            .unwrap_or(self.config.on_external_deletion)
     }
 
-    fn finalizer(&self) -> &'static str {
-        FINALIZER
+    /// Handles a user that was removed from the organization outside the operator according to the policy: invites
+    /// it again, or clears the user ID and records the deletion, so that the user is not looked up again. The
+    /// current generation counts as observed, as the policy was applied to it.
+    async fn handle_external_deletion(
+        &self,
+        atlas_repo: &dyn AtlasUserApi,
+        atlas_user: Arc<AtlasUser>,
+    ) -> KubeResult<Action> {
+        let generation = atlas_user.metadata.generation;
+        let previous = atlas_user.status.clone().unwrap_or_default();
+        let policy = self.external_deletion_policy(&atlas_user);
+
+        if policy == ExternalDeletionPolicy::Recreate {
+            if previous.deleted_externally_at.is_none() {
+                let message = "User was deleted externally from Atlas, inviting it again";
+                let event = EventData::warning(AtlasUserEventReason::DeletedExternally, message);
+                self.event_recorder.emit(atlas_user.as_ref(), event).await;
+            }
+
+            let mut recreated = AtlasUser::clone(&atlas_user);
+            recreated.status = Some(AtlasUserStatus {
+                conditions: previous.conditions,
+                ..Default::default()
+            });
+            return self.invite_user(atlas_repo, Arc::new(recreated)).await;
+        }
+
+        let mut status = AtlasUserStatus {
+            membership_status: Some(UserOrgMembershipStatus::Deleted),
+            deleted_externally_at: Some(previous.deleted_externally_at.unwrap_or_else(Utc::now)),
+            conditions: previous.conditions,
+            ..Default::default()
+        };
+        let message = match policy {
+            ExternalDeletionPolicy::Orphan => {
+                let message = "User was deleted externally from Atlas and is no longer managed";
+                for type_ in [ConditionType::Ready, ConditionType::Invited, ConditionType::Synced] {
+                    status.set_condition(type_, false, "UserOrphaned", message, generation);
+                }
+                status.set_condition(ConditionType::Error, false, "UserOrphaned", message, generation);
+                message
+            }
+            _ => {
+                let message = "User was deleted externally from Atlas";
+                status.error = Some(message.to_string());
+                for type_ in [ConditionType::Ready, ConditionType::Invited, ConditionType::Synced] {
+                    status.set_condition(type_, false, "UserDeletedExternally", message, generation);
+                }
+                status.set_condition(ConditionType::Error, true, "UserDeletedExternally", message, generation);
+                message
+            }
+        };
+
+        if previous.deleted_externally_at.is_none() {
+            let event = EventData::warning(AtlasUserEventReason::DeletedExternally, message);
+            self.event_recorder.emit(atlas_user.as_ref(), event).await;
+        }
+
+        self.update_status(&atlas_user, status).await?;
+
+        Ok(Action::await_change())
     }
 
-    async fn handle_apply(&self, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
+    /// Replaces the expired invitation of the user with a new one, unless the maximum number of re-invitations was
+    /// reached, in which case the expiry is only reported
+    async fn reinvite_user(
+        &self,
+        atlas_repo: &dyn AtlasUserApi,
+        atlas_user: Arc<AtlasUser>,
+        user_id: &str,
+        expired: UserResponse,
+    ) -> KubeResult<Action> {
+        let (name, namespace) = (atlas_user.try_name()?.to_string(), atlas_user.try_namespace()?);
+        let spec = &atlas_user.spec;
+        let generation = atlas_user.metadata.generation;
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        let expires_at = expired
+            .invitation_expires_at
+            .map_or_else(String::new, |expires_at| expires_at.to_rfc3339());
+
+        if status.reinvitations >= self.config.max_reinvitations {
+            let message = format!(
+                "Invitation to organization {} expired at {expires_at}, not inviting again after {} re-invitations",
+                spec.org_id, status.reinvitations
+            );
+            warn!(name = %name, namespace = %namespace, user_id = %user_id, "Invitation expired, re-invitations exhausted");
+
+            if !has_condition(&status, ConditionType::Invited, "InvitationExpired") {
+                let event = EventData::warning(AtlasUserEventReason::InvitationExpired, message.as_str());
+                self.event_recorder.emit(atlas_user.as_ref(), event).await;
+            }
+
+            set_invitation(&mut status, &expired);
+            set_membership(&mut status, expired.org_membership_status, generation);
+            for type_ in [ConditionType::Invited, ConditionType::Ready] {
+                status.set_condition(type_, false, "InvitationExpired", message.as_str(), generation);
+            }
+            self.update_status(&atlas_user, status).await?;
+
+            return Ok(Action::requeue(self.config.requeue_duration));
+        }
+
+        info!(name = %name, namespace = %namespace, user_id = %user_id, "Invitation expired, inviting user again");
+
+        // Atlas has no way to extend an invitation, so the expired one is removed before inviting again. The removal
+        // is recorded right away, so that a failed invitation is sent again by the next reconciliation instead of the
+        // removed user being taken for one deleted outside the operator.
+        ignore_not_found(atlas_repo.delete_atlas_user_from_org(&spec.org_id, user_id).await)?;
+        set_invitation(&mut status, &expired);
+        status.user_id = None;
+        status.reinvitation_pending = true;
+        status.reinvitations += 1;
+        self.update_status(&atlas_user, status.clone()).await?;
+
+        let mut removed = AtlasUser::clone(&atlas_user);
+        removed.status = Some(status);
+        self.send_reinvitation(atlas_repo, Arc::new(removed)).await
+    }
+
+    /// Invites the user again after its expired invitation was removed
+    async fn send_reinvitation(&self, atlas_repo: &dyn AtlasUserApi, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
+        let spec = &atlas_user.spec;
+        let generation = atlas_user.metadata.generation;
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        let expires_at = status
+            .invitation_expires_at
+            .map_or_else(String::new, |expires_at| expires_at.to_rfc3339());
+
+        let request = UserRequest::for_invite(spec);
+        let response = match atlas_repo.invite_atlas_user(&spec.org_id, &request).await {
+            Ok(response) => response,
+            // An earlier invitation went through, but its response was lost
+            Err(Error::UserAlreadyInOrg(error)) => atlas_repo
+                .find_atlas_user_by_username(&spec.org_id, &spec.username)
+                .await?
+                .ok_or(Error::UserAlreadyInOrg(error))?,
+            Err(e) => return Err(e.into()),
+        };
+
+        let message = format!(
+            "Invitation to organization {} expired at {expires_at}, invited {} again (re-invitation {} of {})",
+            spec.org_id, spec.username, status.reinvitations, self.config.max_reinvitations
+        );
+        let event = EventData::normal(AtlasUserEventReason::Reinvited, message.as_str());
+        self.event_recorder.emit(atlas_user.as_ref(), event).await;
+
+        set_invitation(&mut status, &response);
+        status.user_id = Some(response.id);
+        status.reinvitation_pending = false;
+        status.applied_identity = Some(AppliedIdentity::of(spec));
+        set_membership(&mut status, response.org_membership_status, generation);
+        status.set_condition(ConditionType::Invited, true, "Reinvited", message, generation);
+        set_synced(
+            &mut status,
+            "UserReinvited",
+            "User was invited again after its invitation expired",
+            generation,
+        );
+        self.update_status(&atlas_user, status).await?;
+
+        Ok(Action::requeue(self.config.requeue_duration))
+    }
+
+    /// Moves the user to the identity of the spec after its username or organization changed: invites it with the
+    /// new identity and removes the previous membership, if deletion is allowed. The new identity is recorded before
+    /// the previous membership is removed, so that a failed removal is retried on its own.
+    async fn migrate_user(
+        &self,
+        atlas_repo: &dyn AtlasUserApi,
+        atlas_user: Arc<AtlasUser>,
+        user_id: &str,
+        previous: AppliedIdentity,
+    ) -> KubeResult<Action> {
+        let (name, namespace) = (atlas_user.try_name()?.to_string(), atlas_user.try_namespace()?);
+        let spec = &atlas_user.spec;
+        let current = AppliedIdentity::of(spec);
+
+        info!(name = %name, namespace = %namespace, previous = %previous, current = %current, "Migrating user in Atlas");
+
+        let existing = atlas_repo
+            .find_atlas_user_by_username(&spec.org_id, &spec.username)
+            .await?;
+        let (response, origin) = match existing {
+            Some(response) => {
+                self.check_adoption(&atlas_user, &response)?;
+                (response, UserOrigin::Adopted)
+            }
+            None => {
+                let request = UserRequest::for_invite(spec);
+                let response = atlas_repo.invite_atlas_user(&spec.org_id, &request).await?;
+                (response, UserOrigin::Created)
+            }
+        };
+
+        let policy = self.deletion_policy(&atlas_user)?;
+        let (message, previous_membership) = if policy != DeletionPolicy::Delete {
+            let message = format!(
+                "Migrated user from {previous} to {current}, kept the previous membership as the deletion policy is \
+                {policy}"
+            );
+            (message, None)
+        } else {
+            let previous_membership = PreviousMembership {
+                org_id: previous.org_id.clone(),
+                user_id: user_id.to_string(),
+                connection_secret_ref: previous.connection_secret_ref.clone(),
+            };
+            let message = format!("Migrated user from {previous} to {current}, removing the previous membership");
+            (message, Some(previous_membership))
+        };
+        let event = EventData::normal(AtlasUserEventReason::Migrated, message);
+        self.event_recorder.emit(atlas_user.as_ref(), event).await;
+
+        let generation = atlas_user.metadata.generation;
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        status.reinvitations = 0;
+        set_invitation(&mut status, &response);
+        status.user_id = Some(response.id);
+        status.origin = Some(origin);
+        status.applied_identity = Some(current);
+        status.previous_membership = previous_membership.clone();
+        status.drift = None;
+        set_membership(&mut status, response.org_membership_status, generation);
+        set_synced(
+            &mut status,
+            "UserMigrated",
+            "User was invited with the new username or to the new organization",
+            generation,
+        );
+        status.with_observed_gen(&atlas_user.metadata);
+
+        self.update_status(&atlas_user, status.clone()).await?;
+
+        let Some(previous_membership) = previous_membership else {
+            return Ok(Action::requeue(self.config.requeue_duration));
+        };
+        let mut migrated = AtlasUser::clone(&atlas_user);
+        migrated.status = Some(status);
+        self.remove_previous_membership(Arc::new(migrated), previous_membership)
+            .await
+    }
+
+    /// Removes the membership of the user under the identity it was migrated away from, with the credentials that
+    /// identity was applied with
+    async fn remove_previous_membership(
+        &self,
+        atlas_user: Arc<AtlasUser>,
+        previous: PreviousMembership,
+    ) -> KubeResult<Action> {
+        let (name, namespace) = (atlas_user.try_name()?, atlas_user.try_namespace()?);
+        info!(
+            name = %name,
+            namespace = %namespace,
+            user_id = %previous.user_id,
+            org_id = %previous.org_id,
+            "Removing previous membership from Atlas"
+        );
+
+        let atlas_repo = self
+            .atlas_connections
+            .repository_for(&atlas_user, previous.connection_secret_ref.as_ref())
+            .await?;
+        ignore_not_found(
+            atlas_repo
+                .delete_atlas_user_from_org(&previous.org_id, &previous.user_id)
+                .await,
+        )?;
+
+        let message = format!(
+            "Removed previous membership {} from organization {}",
+            previous.user_id, previous.org_id
+        );
+        let event = EventData::normal(AtlasUserEventReason::Removed, message);
+        self.event_recorder.emit(atlas_user.as_ref(), event).await;
+
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        status.previous_membership = None;
+        self.update_status(&atlas_user, status).await?;
+
+        Ok(Action::requeue(self.config.requeue_duration))
+    }
+
+    /// Writes the status to the resource and tracks the user's membership status in the metrics
+    async fn update_status(&self, atlas_user: &AtlasUser, status: AtlasUserStatus) -> KubeResult<()> {
+        self.metrics
+            .set_membership(&metrics_key(atlas_user), status.membership_status.as_ref());
+        self.k8s_repo.update_status(atlas_user, status).await
+    }
+
+    /// Records a failed reconciliation in the status. Failing to do so is only logged, so that the
+    /// original error is reported to the controller.
+    async fn record_error(&self, atlas_user: &AtlasUser, reason: &str, error: &KubeError) {
+        let generation = atlas_user.metadata.generation;
+        let message = error.to_string();
+
+        let event = EventData::warning(AtlasUserEventReason::ReconcileFailed, message.as_str());
+        self.event_recorder.emit(atlas_user, event).await;
+
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        for type_ in [ConditionType::Ready, ConditionType::Synced] {
+            status.set_condition(type_, false, reason, message.as_str(), generation);
+        }
+        status.set_condition(ConditionType::Error, true, reason, message.as_str(), generation);
+
+        // Only the error is patched, keeping what the failed reconciliation already recorded, e.g. a removed
+        // invitation
+        let status = ErrorStatus {
+            error: Some(message),
+            conditions: status.conditions,
+        };
+        if let Err(e) = self.k8s_repo.update_status(atlas_user, status).await {
+            warn!(
+                name = %atlas_user.try_name().unwrap_or_default(),
+                namespace = %atlas_user.try_namespace().unwrap_or_default(),
+                error = %e,
+                "Failed to record reconciliation error in status"
+            );
+        }
+    }
+
+    /// Counts a failed reconciliation of the resource and returns the number of consecutive failures
+    fn count_failure(&self, key: &str) -> u32 {
+        let mut failures = self.failures.lock().expect("failures lock poisoned");
+        let count = failures.entry(key.to_string()).or_default();
+        *count = count.saturating_add(1);
+        *count
+    }
+
+    fn reset_failures(&self, key: &str) {
+        self.failures.lock().expect("failures lock poisoned").remove(key);
+    }
+
+    /// Exponential backoff from the given initial delay for the number of consecutive failures,
+    /// capped at the requeue duration
+    fn backoff(&self, initial: Duration, failures: u32) -> Duration {
+        initial
+            .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+            .min(self.config.requeue_duration)
+    }
+
+    /// Marks the AtlasUser as conflicting with the older one owning the same Atlas user and skips it
+    async fn skip_conflicting(&self, atlas_user: Arc<AtlasUser>, owner: &Owner) -> KubeResult<Action> {
+        let spec = &atlas_user.spec;
+        let message = format!(
+            "User {} in organization {} is already managed by AtlasUser {owner}",
+            spec.username, spec.org_id
+        );
+        warn!(
+            name = %atlas_user.try_name()?,
+            namespace = %atlas_user.try_namespace()?,
+            owner = %owner,
+            "Skipping AtlasUser managing the same user as an older one"
+        );
+
+        let generation = atlas_user.metadata.generation;
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        if !has_conflict(&status) {
+            let event = EventData::warning(AtlasUserEventReason::Conflict, message.as_str());
+            self.event_recorder.emit(atlas_user.as_ref(), event).await;
+        }
+        status.set_condition(
+            ConditionType::Conflict,
+            true,
+            "DuplicateUser",
+            message.as_str(),
+            generation,
+        );
+        status.set_condition(ConditionType::Ready, false, "DuplicateUser", message, generation);
+        self.update_status(&atlas_user, status).await?;
+
+        Ok(Action::requeue(self.config.requeue_duration))
+    }
+
+    /// Applies the spec of the AtlasUser to Atlas
+    async fn apply(&self, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
+        if let Some(owner) = self.index.conflicting_owner(&atlas_user) {
+            return self.skip_conflicting(atlas_user, &owner).await;
+        }
+        let atlas_user = resolve_conflict(atlas_user);
+
         let (name, namespace) = (atlas_user.try_name()?, atlas_user.try_namespace()?);
-        let current_gen = atlas_user.metadata.generation.unwrap_or(1);
 
         // Check if we have a user_id from previous reconciliation
         let user_id = atlas_user
@@ -151,83 +739,435 @@ impl Context<AtlasUser, AtlasUserK8sRepo, StaticApiProvider<AtlasUser>> for Atla
             .and_then(|s| s.user_id.as_ref())
             .map(Arc::clone);
 
+        if let Some(previous) = atlas_user.status.as_ref().and_then(|s| s.previous_membership.clone()) {
+            // A migration recorded the new identity, but failed to remove the previous membership
+            return self.remove_previous_membership(atlas_user, previous).await;
+        }
+
         let needs_update = self.needs_update(&atlas_user);
+        let atlas_repo = self.atlas_connections.repository(&atlas_user).await?;
+
+        if let (Some(user_id), Some(previous)) = (&user_id, changed_identity(&atlas_user)) {
+            return match self.config.identity_change_policy {
+                IdentityChangePolicy::Reject => Err(Error::IdentityChanged {
+                    previous: previous.to_string(),
+                    current: AppliedIdentity::of(&atlas_user.spec).to_string(),
+                }
+                .into()),
+                IdentityChangePolicy::Migrate => {
+                    self.migrate_user(atlas_repo.as_ref(), atlas_user, user_id, previous)
+                        .await
+                }
+            };
+        }
+
+        let deleted_externally = atlas_user
+            .status
+            .as_ref()
+            .is_some_and(|s| s.deleted_externally_at.is_some());
+        if user_id.is_none() && deleted_externally {
+            // Applies the current policy, which may have changed since the deletion was found
+            return self.handle_external_deletion(atlas_repo.as_ref(), atlas_user).await;
+        }
+
+        let reinvitation_pending = atlas_user.status.as_ref().is_some_and(|s| s.reinvitation_pending);
+        if user_id.is_none() && reinvitation_pending {
+            // The expired invitation was removed, but inviting the user again failed
+            return self.send_reinvitation(atlas_repo.as_ref(), atlas_user).await;
+        }
 
-        match (user_id, needs_update, current_gen) {
-            (Some(user_id), true, _) => {
+        match (user_id, needs_update) {
+            (Some(user_id), true) => {
                 // User exists and spec changed -> update
-                self.update_user(atlas_user, &user_id).await
+                self.update_user(atlas_repo.as_ref(), atlas_user, &user_id).await
             }
-            (Some(user_id), false, _) => {
+            (Some(user_id), false) => {
                 // User exists and spec unchanged -> sync status
-                self.sync_status(atlas_user, &user_id).await
-            }
-            (None, _, 1) => {
-                // New resource (generation == 1 and no user_id) -> invite
-                self.invite_user(atlas_user).await
+                self.sync_status(atlas_repo.as_ref(), atlas_user, &user_id).await
             }
-            (None, _, _) => {
-                // No user_id but generation > 1, try to find by username
+            (None, _) => {
+                // No user_id yet, take over an existing member of the organization before inviting
                 let spec = &atlas_user.spec;
                 info!(name = %name, namespace = %namespace, "Looking up user by username");
 
-                match self
-                    .atlas_repo
+                match atlas_repo
                     .find_atlas_user_by_username(&spec.org_id, &spec.username)
                     .await?
                 {
-                    Some(response) => {
-                        // Found the user, update status and proceed
-                        let mut status = atlas_user.status.clone().unwrap_or_default();
-                        status.user_id = Some(Arc::clone(&response.id));
-                        status.membership_status = Some(response.org_membership_status);
-                        status.error = None;
-                        status.with_observed_gen(&atlas_user.metadata);
-
-                        self.k8s_repo.update_status(&atlas_user, status).await?;
-
-                        Ok(Action::requeue(Duration::from_secs(1))) // Requeue immediately to process with user_id
-                    }
+                    Some(response) => self.adopt_user(atlas_user, response).await,
                     None => {
                         // User doesn't exist in Atlas, create new
-                        self.invite_user(atlas_user).await
+                        self.invite_user(atlas_repo.as_ref(), atlas_user).await
                     }
                 }
             }
         }
     }
 
-    async fn handle_cleanup(&self, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
+    /// Removes the user or the project roles of the AtlasUser from Atlas, as its deletion policy says
+    async fn cleanup(&self, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
         let (name, namespace) = (atlas_user.try_name()?, atlas_user.try_namespace()?);
 
-        if !self.config.safe_to_delete {
+        if let Some(owner) = self.index.conflicting_owner(&atlas_user) {
             info!(
                 name = %name,
                 namespace = %namespace,
-                "safe_to_delete is false, skipping Atlas user deletion"
+                owner = %owner,
+                "User is managed by an older AtlasUser, skipping Atlas user deletion"
             );
             return Ok(Action::await_change());
         }
 
-        let user_id = atlas_user
-            .status
-            .as_ref()
-            .and_then(|s| s.user_id.as_ref())
-            .ok_or_else(|| KubeError::UserInput("Cannot delete user without user_id in status".to_string()))?;
+        let policy = self.deletion_policy(&atlas_user)?;
+        if policy == DeletionPolicy::Retain {
+            info!(
+                name = %name,
+                namespace = %namespace,
+                "Deletion policy is Retain, skipping Atlas user deletion"
+            );
+
+            let mut status = atlas_user.status.clone().unwrap_or_default();
+            status.set_condition(
+                ConditionType::Ready,
+                false,
+                "UserRetained",
+                "Resource is being deleted, the user is kept in Atlas",
+                atlas_user.metadata.generation,
+            );
+            self.update_status(&atlas_user, status).await?;
+
+            return Ok(Action::await_change());
+        }
+
+        // Without a user ID the resource never got hold of a user, e.g. because it was deleted externally or its
+        // adoption was refused
+        let Some(user_id) = atlas_user.status.as_ref().and_then(|s| s.user_id.as_ref()) else {
+            info!(name = %name, namespace = %namespace, "No user recorded in status, nothing to delete in Atlas");
+            return Ok(Action::await_change());
+        };
+        // The user lives in the organization it was last applied to, which differs from the spec's after an
+        // identity change that was not migrated, and is removed with the credentials it was applied with
+        let applied_identity = atlas_user.status.as_ref().and_then(|s| s.applied_identity.as_ref());
+        let (org_id, secret_ref) = match applied_identity {
+            Some(identity) => (identity.org_id.as_str(), identity.connection_secret_ref.as_ref()),
+            None => (
+                atlas_user.spec.org_id.as_str(),
+                atlas_user.spec.connection_secret_ref.as_ref(),
+            ),
+        };
+        let atlas_repo = self.atlas_connections.repository_for(&atlas_user, secret_ref).await?;
+
+        if policy == DeletionPolicy::RemoveProjectRolesOnly {
+            return self
+                .remove_project_roles(atlas_repo.as_ref(), &atlas_user, org_id, user_id)
+                .await;
+        }
 
         info!(name = %name, namespace = %namespace, user_id = %user_id, "Deleting user from Atlas");
 
-        self.atlas_repo
-            .delete_atlas_user_from_org(&atlas_user.spec.org_id, user_id)
-            .await?;
+        if let Some(previous) = atlas_user.status.as_ref().and_then(|s| s.previous_membership.as_ref()) {
+            let previous_repo = self
+                .atlas_connections
+                .repository_for(&atlas_user, previous.connection_secret_ref.as_ref())
+                .await?;
+            ignore_not_found(
+                previous_repo
+                    .delete_atlas_user_from_org(&previous.org_id, &previous.user_id)
+                    .await,
+            )?;
+        }
+        ignore_not_found(atlas_repo.delete_atlas_user_from_org(org_id, user_id).await)?;
+
+        let message = format!("Removed user {} from organization {}", user_id, org_id);
+        let event = EventData::normal(AtlasUserEventReason::Removed, message);
+        self.event_recorder.emit(atlas_user.as_ref(), event).await;
 
         // Update status to deleted
+        let generation = atlas_user.metadata.generation;
+        let mut status = atlas_user.status.clone().unwrap_or_default();
+        set_membership(&mut status, UserOrgMembershipStatus::Deleted, generation);
+        set_synced(
+            &mut status,
+            "UserDeleted",
+            "User was removed from the organization",
+            generation,
+        );
+
+        self.update_status(&atlas_user, status).await?;
+
+        Ok(Action::await_change())
+    }
+
+    /// Removes the project roles the AtlasUser granted from the user, keeping it in the organization with its
+    /// organization roles, teams and project roles granted otherwise
+    async fn remove_project_roles(
+        &self,
+        atlas_repo: &dyn AtlasUserApi,
+        atlas_user: &AtlasUser,
+        org_id: &str,
+        user_id: &str,
+    ) -> KubeResult<Action> {
+        let (name, namespace) = (atlas_user.try_name()?, atlas_user.try_namespace()?);
+        info!(name = %name, namespace = %namespace, user_id = %user_id, "Removing project roles from user in Atlas");
+
+        let current = match atlas_repo.get_atlas_user(org_id, user_id).await {
+            Ok(current) => current,
+            Err(Error::AtlasUserNotFound { .. }) => {
+                info!(name = %name, namespace = %namespace, "User not found in Atlas, no project roles to remove");
+                return Ok(Action::await_change());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let roles = current
+            .roles
+            .without_group_roles(&atlas_user.spec.roles.group_role_assignments);
+        let request = UserRequest {
+            username: None,
+            roles: &roles,
+            team_ids: &current.team_ids,
+        };
+        let response = atlas_repo.update_atlas_user(org_id, user_id, &request).await?;
+
+        let message = format!("Removed project roles of this resource from user {user_id}");
+        let event = EventData::normal(AtlasUserEventReason::RolesRemoved, message);
+        self.event_recorder.emit(atlas_user, event).await;
+
+        let generation = atlas_user.metadata.generation;
         let mut status = atlas_user.status.clone().unwrap_or_default();
-        status.membership_status = Some(UserOrgMembershipStatus::Deleted);
-        status.error = None;
+        set_membership(&mut status, response.org_membership_status, generation);
+        set_synced(
+            &mut status,
+            "ProjectRolesRemoved",
+            "Project roles of this resource were removed, the user is kept in Atlas",
+            generation,
+        );
+        status.set_condition(
+            ConditionType::Ready,
+            false,
+            "ProjectRolesRemoved",
+            "Resource is being deleted, the user is kept in Atlas without its project roles",
+            generation,
+        );
 
-        self.k8s_repo.update_status(&atlas_user, status).await?;
+        self.update_status(atlas_user, status).await?;
 
         Ok(Action::await_change())
     }
+
+    /// The deletion policy of the AtlasUser: from its annotation, its spec, or `safe_to_delete` and the origin of the
+    /// user, in that order
+    fn deletion_policy(&self, atlas_user: &AtlasUser) -> KubeResult<DeletionPolicy> {
+        if let Some(value) = atlas_user.annotations().get(DELETION_POLICY_ANNOTATION) {
+            return value.parse().map_err(|_| {
+                KubeError::UserInput(format!(
+                    "Invalid {DELETION_POLICY_ANNOTATION} annotation {value:?}, expected Delete, Retain or \
+                    RemoveProjectRolesOnly"
+                ))
+            });
+        }
+
+        // Users the operator did not invite are only removed if a deletion policy asks for it explicitly
+        let adopted = atlas_user.status.as_ref().and_then(|s| s.origin) == Some(UserOrigin::Adopted);
+        Ok(atlas_user
+            .spec
+            .deletion_policy
+            .unwrap_or(if self.config.safe_to_delete && !adopted {
+                DeletionPolicy::Delete
+            } else {
+                DeletionPolicy::Retain
+            }))
+    }
+}
+
+/// The part of the status recording a failed reconciliation
+#[derive(Serialize, Debug)]
+struct ErrorStatus {
+    error: Option<String>,
+    conditions: Vec<Condition>,
+}
+
+impl ObserveGeneration for ErrorStatus {
+    /// A failed reconciliation did not apply the generation
+    fn add(&mut self, _observed_generation: i64) {}
+}
+
+/// Returns the Atlas error behind a reconciliation error, if any
+fn atlas_error(error: &KubeError) -> Option<&Error> {
+    match error {
+        KubeError::Anyhow(error) => error.downcast_ref(),
+        _ => None,
+    }
+}
+
+/// Treats the removal of a user that is already gone as successful
+fn ignore_not_found(result: Result<(), Error>) -> Result<(), Error> {
+    match result {
+        Err(Error::AtlasUserNotFound { .. } | Error::ResourceNotFound(_)) => Ok(()),
+        result => result,
+    }
+}
+
+/// Identifies an AtlasUser in the metrics
+fn metrics_key(atlas_user: &AtlasUser) -> String {
+    format!(
+        "{}/{}",
+        atlas_user.try_namespace().unwrap_or_default(),
+        atlas_user.try_name().unwrap_or_default()
+    )
+}
+
+/// Records the membership status reported by Atlas along with the Invited and Ready conditions derived from it
+fn set_membership(status: &mut AtlasUserStatus, membership: UserOrgMembershipStatus, generation: Option<i64>) {
+    let (invited, ready, reason, message) = match membership {
+        UserOrgMembershipStatus::Active => (true, true, "UserActive", "User is an active member of the organization"),
+        UserOrgMembershipStatus::Pending => (
+            true,
+            false,
+            "InvitationPending",
+            "User was invited and has not accepted the invitation yet",
+        ),
+        UserOrgMembershipStatus::Deleted => (false, false, "UserDeleted", "User was removed from the organization"),
+    };
+
+    status.membership_status = Some(membership);
+    status.set_condition(ConditionType::Invited, invited, reason, message, generation);
+    status.set_condition(ConditionType::Ready, ready, reason, message, generation);
+}
+
+/// The identity the user was last applied with, if the username or organization of the spec changed since.
+/// Resources reconciled before the identity was recorded are assumed to match their spec.
+fn changed_identity(atlas_user: &AtlasUser) -> Option<AppliedIdentity> {
+    let applied = atlas_user.status.as_ref()?.applied_identity.as_ref()?;
+    (!applied.is_same(&AppliedIdentity::of(&atlas_user.spec))).then(|| applied.clone())
+}
+
+/// Records when the pending invitation of the user was created and expires, cleared once it was accepted
+fn set_invitation(status: &mut AtlasUserStatus, response: &UserResponse) {
+    status.invitation_created_at = response.invitation_created_at;
+    status.invitation_expires_at = response.invitation_expires_at;
+}
+
+/// Whether the user has not accepted its invitation before it expired
+fn invitation_expired(response: &UserResponse) -> bool {
+    response.org_membership_status == UserOrgMembershipStatus::Pending
+        && response
+            .invitation_expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+}
+
+/// Whether the condition of the given type is set with the given reason
+fn has_condition(status: &AtlasUserStatus, type_: ConditionType, reason: &str) -> bool {
+    status
+        .conditions
+        .iter()
+        .any(|condition| condition.type_ == type_ && condition.reason == reason)
+}
+
+/// Whether the status reports a conflict with an older AtlasUser
+fn has_conflict(status: &AtlasUserStatus) -> bool {
+    status
+        .conditions
+        .iter()
+        .any(|condition| condition.type_ == ConditionType::Conflict && condition.status == ConditionStatus::True)
+}
+
+/// Clears a conflict reported earlier, now that no older AtlasUser manages the same user anymore
+fn resolve_conflict(atlas_user: Arc<AtlasUser>) -> Arc<AtlasUser> {
+    let Some(status) = atlas_user.status.as_ref().filter(|status| has_conflict(status)) else {
+        return atlas_user;
+    };
+
+    let mut status = status.clone();
+    status.set_condition(
+        ConditionType::Conflict,
+        false,
+        "ConflictResolved",
+        "No older AtlasUser manages the same user",
+        atlas_user.metadata.generation,
+    );
+
+    let mut resolved = AtlasUser::clone(&atlas_user);
+    resolved.status = Some(status);
+    Arc::new(resolved)
+}
+
+/// Marks the spec as successfully applied to or synced from Atlas and clears any previous error
+fn set_synced(status: &mut AtlasUserStatus, reason: &str, message: &str, generation: Option<i64>) {
+    status.error = None;
+    status.set_condition(ConditionType::Synced, true, reason, message, generation);
+    status.set_condition(ConditionType::Error, false, "ReconcileSucceeded", "", generation);
+}
+
+#[async_trait]
+impl Context<AtlasUser, AtlasUserK8sRepo, StaticApiProvider<AtlasUser>> for AtlasUserContext {
+    fn k8s_repository(&self) -> Arc<K8sRepository<AtlasUser, StaticApiProvider<AtlasUser>>> {
+        Arc::clone(&self.k8s_repo)
+    }
+
+    fn finalizer(&self) -> &'static str {
+        FINALIZER
+    }
+
+    async fn handle_apply(&self, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
+        let start = Instant::now();
+        let result = self.apply(Arc::clone(&atlas_user)).await;
+        self.metrics.observe_reconcile("apply", result.is_ok(), start.elapsed());
+
+        let key = metrics_key(&atlas_user);
+        let error = match result {
+            Ok(action) => {
+                self.reset_failures(&key);
+                return Ok(action);
+            }
+            Err(error) => error,
+        };
+
+        let class = atlas_error(&error).map_or(ErrorClass::Transient, Error::class);
+        let failures = self.count_failure(&key);
+        let (reason, action) = match class {
+            ErrorClass::InvalidSpec => ("InvalidSpec", Action::await_change()),
+            ErrorClass::Permanent => ("PermanentFailure", Action::requeue(self.config.requeue_duration)),
+            ErrorClass::RateLimited => (
+                "RateLimited",
+                Action::requeue(self.backoff(RATE_LIMITED_BACKOFF, failures)),
+            ),
+            ErrorClass::Transient => (
+                "ReconcileFailed",
+                Action::requeue(self.backoff(TRANSIENT_BACKOFF, failures)),
+            ),
+        };
+
+        warn!(
+            name = %atlas_user.try_name().unwrap_or_default(),
+            namespace = %atlas_user.try_namespace().unwrap_or_default(),
+            error = %error,
+            class = ?class,
+            failures,
+            action = ?action,
+            "Reconciliation failed"
+        );
+        self.record_error(&atlas_user, reason, &error).await;
+
+        Ok(action)
+    }
+
+    async fn handle_cleanup(&self, atlas_user: Arc<AtlasUser>) -> KubeResult<Action> {
+        let start = Instant::now();
+        let result = self.cleanup(Arc::clone(&atlas_user)).await;
+        match &result {
+            // The finalizer is removed after a successful cleanup, so the user is no longer managed
+            Ok(_) => {
+                let key = metrics_key(&atlas_user);
+                self.metrics.set_membership(&key, None);
+                self.reset_failures(&key);
+                self.atlas_connections.release(&atlas_user);
+            }
+            Err(error) => self.record_error(&atlas_user, "ReconcileFailed", error).await,
+        }
+
+        self.metrics
+            .observe_reconcile("cleanup", result.is_ok(), start.elapsed());
+        result
+    }
 }
@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::atlas::user_response::UserResponse;
+use crate::crd::AtlasUserRoles;
+use crate::crd::AtlasUserSpec;
+use crate::crd::GroupRoleName;
+use crate::crd::OrgRoleName;
+
+/// Compares the desired state from the spec with the user as reported by Atlas, ignoring the order of
+/// roles, role assignments and teams. Returns the spec fields that differ.
+pub fn detect(spec: &AtlasUserSpec, response: &UserResponse) -> Vec<String> {
+    let mut drift = Vec::new();
+
+    if org_roles(&spec.roles) != org_roles(&response.roles) {
+        drift.push("roles.orgRoles".to_string());
+    }
+    if group_roles(&spec.roles) != group_roles(&response.roles) {
+        drift.push("roles.groupRoleAssignments".to_string());
+    }
+    if team_ids(&spec.team_ids) != team_ids(&response.team_ids) {
+        drift.push("teamIds".to_string());
+    }
+
+    drift
+}
+
+fn org_roles(roles: &AtlasUserRoles) -> BTreeSet<&OrgRoleName> {
+    roles.org_roles.iter().collect()
+}
+
+/// Group roles keyed by group ID. Groups without any roles are ignored, as they grant nothing.
+fn group_roles(roles: &AtlasUserRoles) -> BTreeMap<&str, BTreeSet<&GroupRoleName>> {
+    let mut group_roles: BTreeMap<&str, BTreeSet<&GroupRoleName>> = BTreeMap::new();
+    for assignment in &roles.group_role_assignments {
+        group_roles
+            .entry(assignment.group_id.as_str())
+            .or_default()
+            .extend(&assignment.group_roles);
+    }
+    group_roles.retain(|_, roles| !roles.is_empty());
+    group_roles
+}
+
+fn team_ids(team_ids: &[String]) -> BTreeSet<&str> {
+    team_ids.iter().map(String::as_str).collect()
+}
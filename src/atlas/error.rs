@@ -1,7 +1,11 @@
 use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::PathBuf;
 
 use kuberator::error::Error as KubeError;
 use reqwest::StatusCode;
+use serde::Deserialize;
 use thiserror::Error as ThisError;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -10,18 +14,158 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     #[error("Kubernetes reported error: {0}")]
     K8s(#[from] kube::Error),
+    #[error("Kuberator error: {0}")]
+    Kuberator(#[from] KubeError),
     #[error("serde_json error: {0}")]
     Json(#[from] serde_json::Error),
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
-    #[error("Unexpected response from MongoDB Atlas API: {status}. Message: {message}.")]
-    Api { status: StatusCode, message: String },
+    #[error("Invalid header value: {0}")]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+    #[error("Digest authentication error: {0}")]
+    DigestAuth(#[from] digest_auth::Error),
+    #[error("Unexpected response from MongoDB Atlas API: {status}. {error}")]
+    Api { status: StatusCode, error: AtlasApiError },
+    #[error("User is already a member of the organization: {0}")]
+    UserAlreadyInOrg(AtlasApiError),
+    #[error("Invalid role for user: {0}")]
+    InvalidRoleForUser(AtlasApiError),
+    #[error("Atlas resource not found: {0}")]
+    ResourceNotFound(AtlasApiError),
+    #[error("Credentials lack the Organization Project Creator role: {0}")]
+    NotOrgGroupCreator(AtlasApiError),
+    #[error("Rate limited by Atlas: {0}")]
+    RateLimited(AtlasApiError),
+    #[error("Failed to obtain Atlas access token: {status}. Message: {message}.")]
+    TokenRequest { status: StatusCode, message: String },
     #[error("Atlas user {user_id} not found in organization {org_id}")]
     AtlasUserNotFound { user_id: String, org_id: String },
+    #[error("No connection Secret referenced and no default Atlas credentials configured")]
+    MissingCredentials,
+    #[error("Invalid Atlas connection Secret {name}: {reason}")]
+    InvalidConnectionSecret { name: String, reason: String },
+    #[error("Failed to read CA bundle {path}: {source}")]
+    CaBundle { path: PathBuf, source: std::io::Error },
+    #[error("Changing the user from {previous} to {current} is not allowed, revert the username and orgId")]
+    IdentityChanged { previous: String, current: String },
+    #[error("User {username} already is a member of organization {org_id} and is not adopted: {reason}")]
+    AdoptionRefused {
+        username: String,
+        org_id: String,
+        reason: String,
+    },
     #[error("Status object not set yet")]
     StatusObjectNotSet,
 }
 
+impl Error {
+    /// Classifies the error by whether and how the failed operation should be retried
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::InvalidRoleForUser(_) | Error::IdentityChanged { .. } => ErrorClass::InvalidSpec,
+            Error::Api { status, .. }
+                if matches!(*status, StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY) =>
+            {
+                ErrorClass::InvalidSpec
+            }
+            Error::RateLimited(_) => ErrorClass::RateLimited,
+            Error::Api { status, .. } | Error::TokenRequest { status, .. }
+                if status.is_server_error()
+                    || matches!(*status, StatusCode::REQUEST_TIMEOUT | StatusCode::CONFLICT) =>
+            {
+                ErrorClass::Transient
+            }
+            Error::Api { .. }
+            | Error::TokenRequest { .. }
+            | Error::NotOrgGroupCreator(_)
+            | Error::UserAlreadyInOrg(_)
+            | Error::ResourceNotFound(_)
+            | Error::AdoptionRefused { .. }
+            | Error::MissingCredentials
+            | Error::InvalidConnectionSecret { .. }
+            | Error::InvalidHeader(_)
+            | Error::DigestAuth(_)
+            | Error::CaBundle { .. }
+            | Error::Json(_) => ErrorClass::Permanent,
+            Error::K8s(_)
+            | Error::Kuberator(_)
+            | Error::Reqwest(_)
+            | Error::AtlasUserNotFound { .. }
+            | Error::StatusObjectNotSet => ErrorClass::Transient,
+        }
+    }
+
+    /// Whether retrying the failed operation without any change may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.class(), ErrorClass::RateLimited | ErrorClass::Transient)
+    }
+}
+
+/// How a failed operation should be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Atlas rejected the spec of the resource, retrying can't succeed until it changes
+    InvalidSpec,
+    /// Retrying can't succeed until something outside of the resource changes, e.g. permissions or credentials
+    Permanent,
+    /// Atlas kept rate limiting the request
+    RateLimited,
+    /// Network failures, server errors and conflicts that are likely gone by the next attempt
+    Transient,
+}
+
+/// Error body returned by the Atlas Admin API
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AtlasApiError {
+    /// Machine-readable error code, e.g. `USER_ALREADY_IN_ORG`
+    #[serde(default)]
+    pub error_code: Option<String>,
+    /// Human-readable description of the error
+    #[serde(default)]
+    pub detail: Option<String>,
+    /// Reason phrase of the HTTP status
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Values the detail message refers to
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+}
+
+impl AtlasApiError {
+    /// Parses an Atlas error body. Bodies that are not JSON, e.g. from proxies, are kept as the detail.
+    pub fn from_body(body: &str) -> Self {
+        serde_json::from_str(body).unwrap_or_else(|_| AtlasApiError {
+            detail: Some(body.to_string()).filter(|body| !body.is_empty()),
+            ..AtlasApiError::default()
+        })
+    }
+
+    /// Maps well-known error codes to dedicated error variants
+    pub fn into_error(self, status: StatusCode) -> Error {
+        match self.error_code.as_deref() {
+            Some("USER_ALREADY_IN_ORG") => Error::UserAlreadyInOrg(self),
+            Some("INVALID_ROLE_FOR_USER") => Error::InvalidRoleForUser(self),
+            Some("RESOURCE_NOT_FOUND") => Error::ResourceNotFound(self),
+            Some("NOT_ORG_GROUP_CREATOR") => Error::NotOrgGroupCreator(self),
+            Some("RATE_LIMITED") => Error::RateLimited(self),
+            _ if status == StatusCode::TOO_MANY_REQUESTS => Error::RateLimited(self),
+            _ => Error::Api { status, error: self },
+        }
+    }
+}
+
+impl Display for AtlasApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.error_code, &self.detail) {
+            (Some(error_code), Some(detail)) => write!(f, "{error_code}: {detail}"),
+            (Some(error_code), None) => write!(f, "{error_code}"),
+            (None, Some(detail)) => write!(f, "{detail}"),
+            (None, None) => write!(f, "{}", self.reason.as_deref().unwrap_or("no details")),
+        }
+    }
+}
+
 impl From<Error> for KubeError {
     fn from(error: Error) -> KubeError {
         KubeError::Anyhow(anyhow::anyhow!(error))
@@ -0,0 +1,32 @@
+use kuberator::events::Reason;
+use strum::AsRefStr;
+use strum::Display;
+
+/// Reasons of the Kubernetes Events emitted on AtlasUser resources
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, AsRefStr)]
+pub enum AtlasUserEventReason {
+    /// The user was invited to the organization
+    Invited,
+    /// The user already was a member of the organization and was taken over
+    Adopted,
+    /// The roles and teams of the user were applied to Atlas
+    Updated,
+    /// The invitation of the user expired and it was invited again
+    Reinvited,
+    /// The invitation of the user expired and the maximum number of re-invitations was reached
+    InvitationExpired,
+    /// The user was removed from the organization
+    Removed,
+    /// The project roles granted by a deleted AtlasUser were removed from the user
+    RolesRemoved,
+    /// The user was invited with the new username or to the new organization of the spec
+    Migrated,
+    /// The user was removed from the organization outside the operator
+    DeletedExternally,
+    /// A reconciliation failed, e.g. because the Atlas API rejected a request
+    ReconcileFailed,
+    /// An older AtlasUser manages the same user
+    Conflict,
+}
+
+impl Reason for AtlasUserEventReason {}
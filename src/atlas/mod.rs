@@ -1,8 +1,19 @@
+pub mod api;
+pub mod auth;
+pub mod client;
+pub mod connection;
 pub mod context;
+pub mod drift;
 pub mod error;
+pub mod events;
+pub mod rate_limit;
 pub mod repository;
+pub mod retry;
 pub mod user_request;
 pub mod user_response;
 
+pub use api::AtlasUserApi;
+pub use client::AtlasClient;
+pub use connection::AtlasConnections;
 pub use context::AtlasUserContext;
 pub use repository::AtlasUserRepository;
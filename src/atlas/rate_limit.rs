@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::config::RateLimitBudget;
+use crate::config::RateLimitConfig;
+
+/// Client-side token bucket rate limiter for Atlas requests, keyed by organization.
+///
+/// Atlas enforces its rate limits per organization, so one limiter is shared by the repositories of all
+/// credentials. Requests wait for a token instead of failing. Tokens are reserved in order, so a bucket may go
+/// into debt and each waiting request sleeps until its reserved token has been refilled.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits until a request to the given organization fits into its budget
+    pub async fn acquire(&self, org_id: &str) {
+        let wait = self.reserve(org_id);
+        if !wait.is_zero() {
+            debug!(org_id, ?wait, "Waiting for Atlas rate limit budget");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes a token from the bucket of the organization and returns how long to wait until it is available
+    fn reserve(&self, org_id: &str) -> Duration {
+        let budget = self.budget(org_id);
+        let rate = f64::from(budget.requests_per_minute) / 60.0;
+        let capacity = f64::from(budget.burst.max(1));
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        let bucket = buckets.entry(org_id.to_string()).or_insert(TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let refilled = now.duration_since(bucket.last_refill).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refilled).min(capacity) - 1.0;
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 0.0 || rate <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+
+    fn budget(&self, org_id: &str) -> &RateLimitBudget {
+        self.config.orgs.get(org_id).unwrap_or(&self.config.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AtlasApiConfig;
+
+    const ORG_ID: &str = "5a0a1e7e0f2912c554080adc";
+    const OTHER_ORG_ID: &str = "0f1e2d3c4b5a69788796a5b4";
+
+    fn limiter(requests_per_minute: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            default: RateLimitBudget {
+                requests_per_minute,
+                burst,
+            },
+            orgs: HashMap::new(),
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_is_sent_without_waiting() {
+        let rate_limiter = limiter(60, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            rate_limiter.acquire(ORG_ID).await;
+        }
+
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drained_bucket_waits_for_reserved_tokens() {
+        let rate_limiter = limiter(60, 2);
+        rate_limiter.acquire(ORG_ID).await;
+        rate_limiter.acquire(ORG_ID).await;
+
+        let waits: Vec<_> = (0..3).map(|_| rate_limiter.reserve(ORG_ID)).collect();
+
+        assert_eq!(
+            waits,
+            [Duration::from_secs(1), Duration::from_secs(2), Duration::from_secs(3)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire_sleeps_until_token_is_refilled() {
+        let rate_limiter = limiter(60, 1);
+        rate_limiter.acquire(ORG_ID).await;
+        let start = Instant::now();
+
+        rate_limiter.acquire(ORG_ID).await;
+        rate_limiter.acquire(ORG_ID).await;
+
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refill_is_capped_at_burst() {
+        let rate_limiter = limiter(60, 2);
+        rate_limiter.acquire(ORG_ID).await;
+        rate_limiter.acquire(ORG_ID).await;
+
+        tokio::time::advance(Duration::from_secs(60)).await;
+
+        assert_eq!(rate_limiter.reserve(ORG_ID), Duration::ZERO);
+        assert_eq!(rate_limiter.reserve(ORG_ID), Duration::ZERO);
+        assert_eq!(rate_limiter.reserve(ORG_ID), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn organizations_have_independent_budgets() {
+        let rate_limiter = RateLimiter::new(RateLimitConfig {
+            default: RateLimitBudget {
+                requests_per_minute: 60,
+                burst: 1,
+            },
+            orgs: HashMap::from([(
+                OTHER_ORG_ID.to_string(),
+                RateLimitBudget {
+                    requests_per_minute: 30,
+                    burst: 1,
+                },
+            )]),
+        });
+        rate_limiter.acquire(ORG_ID).await;
+
+        assert_eq!(rate_limiter.reserve(OTHER_ORG_ID), Duration::ZERO);
+        assert_eq!(rate_limiter.reserve(ORG_ID), Duration::from_secs(1));
+        assert_eq!(rate_limiter.reserve(OTHER_ORG_ID), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn zero_requests_per_minute_is_rejected() {
+        let zero = RateLimitBudget {
+            requests_per_minute: 0,
+            burst: 1,
+        };
+        let default_zero = AtlasApiConfig {
+            rate_limit: RateLimitConfig {
+                default: zero.clone(),
+                orgs: HashMap::new(),
+            },
+            ..AtlasApiConfig::default()
+        };
+        let org_zero = AtlasApiConfig {
+            rate_limit: RateLimitConfig {
+                default: RateLimitBudget::default(),
+                orgs: HashMap::from([(ORG_ID.to_string(), zero)]),
+            },
+            ..AtlasApiConfig::default()
+        };
+
+        assert!(default_zero.validate().is_err());
+        assert!(org_zero.validate().is_err());
+        assert!(AtlasApiConfig::default().validate().is_ok());
+    }
+}
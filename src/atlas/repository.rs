@@ -1,53 +1,173 @@
 use std::sync::Arc;
+use std::time::Instant;
 
-use reqwest::header;
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::Request;
+use reqwest::RequestBuilder;
 use reqwest::Response;
 use reqwest::StatusCode;
 use serde::Deserialize;
+use tracing::field;
+use tracing::info_span;
+use tracing::warn;
+use tracing::Instrument;
+use tracing::Span;
 
+use crate::atlas::api::AtlasUserApi;
+use crate::atlas::auth::Authenticator;
+use crate::atlas::auth::Credentials;
+use crate::atlas::client::AtlasClient;
+use crate::atlas::error::AtlasApiError;
 use crate::atlas::error::Error;
 use crate::atlas::error::Result;
 use crate::atlas::user_request::UserRequest;
 use crate::atlas::user_response::UserResponse;
 
-const ATLAS_API_CONTENT_TYPE_2025_02_19: &str = "application/vnd.atlas.2025-02-19+json";
-const ATLAS_API_V2_BASE_URL: &str = "https://cloud.mongodb.com/api/atlas/v2";
+const ITEMS_PER_PAGE: usize = 500;
+
+/// Endpoint path templates, used as metric labels
+const ORGS_ENDPOINT: &str = "/orgs";
+const ORG_USERS_ENDPOINT: &str = "/orgs/{orgId}/users";
+const ORG_USER_ENDPOINT: &str = "/orgs/{orgId}/users/{userId}";
 
 /// Repository for interacting with the MongoDB Atlas Admin API v2
 pub struct AtlasUserRepository {
-    client: Client,
-    access_token: Arc<str>,
+    client: Arc<AtlasClient>,
+    authenticator: Authenticator,
 }
 
 impl AtlasUserRepository {
-    /// Creates a new AtlasUserRepository with a bearer token for authentication
-    pub fn new(access_token: Arc<str>) -> Result<Self> {
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::CONTENT_TYPE,
-            header::HeaderValue::from_static("application/json; charset=utf-8"),
-        );
-        headers.insert(
-            header::ACCEPT,
-            header::HeaderValue::from_static(ATLAS_API_CONTENT_TYPE_2025_02_19),
+    /// Creates a new AtlasUserRepository that authenticates with the given credentials
+    pub fn new(credentials: Credentials, client: Arc<AtlasClient>) -> Self {
+        let authenticator = Authenticator::new(client.http.clone(), Arc::clone(&client.token_url), credentials);
+
+        Self { client, authenticator }
+    }
+
+    /// Fetches every page of a paginated Atlas list endpoint, applying the given query filters
+    async fn list_paginated<A>(&self, endpoint: &str, url: &str, filters: &[(&str, &str)]) -> Result<Vec<A>>
+    where
+        A: for<'de> Deserialize<'de>,
+    {
+        let mut items = Vec::new();
+        let mut page_num = 1;
+
+        loop {
+            let request = self
+                .client
+                .http
+                .get(url)
+                .query(filters)
+                .query(&[("pageNum", page_num), ("itemsPerPage", ITEMS_PER_PAGE)])
+                .query(&[("includeCount", true)]);
+            let response = self.send(endpoint, request).await?;
+
+            let page: PaginatedResponse<A> = match response.status() {
+                StatusCode::OK => handle_ok_response(response).await?,
+                status => return handle_error(status, response).await,
+            };
+
+            let page_len = page.results.len();
+            items.extend(page.results);
+
+            let exhausted = match page.total_count {
+                Some(total_count) => items.len() >= total_count,
+                None => page_len < ITEMS_PER_PAGE,
+            };
+            if exhausted || page_len == 0 {
+                return Ok(items);
+            }
+
+            page_num += 1;
+        }
+    }
+
+    /// Sends an authenticated request, retrying it as long as the retry policy allows.
+    /// The attempt count is recorded on the `atlas_request` span.
+    async fn send(&self, endpoint: &str, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        let span = info_span!(
+            "atlas_request",
+            method = %request.method(),
+            endpoint,
+            attempt = field::Empty
         );
 
-        let client = Client::builder().default_headers(headers).build()?;
+        self.send_with_retry(endpoint, request).instrument(span).await
+    }
+
+    async fn send_with_retry(&self, endpoint: &str, mut request: Request) -> Result<Response> {
+        let method = request.method().clone();
+        let mut attempt = 1;
+
+        loop {
+            Span::current().record("attempt", attempt);
+
+            let retry = request.try_clone();
+            let outcome = self.send_authorized(endpoint, request).await;
+
+            let (Some(retry), Some(backoff)) = (retry, self.client.retry_policy.backoff(&method, &outcome, attempt))
+            else {
+                return outcome;
+            };
+
+            match &outcome {
+                Ok(response) => warn!(status = %response.status(), ?backoff, "Retrying Atlas request"),
+                Err(e) => warn!(error = %e, ?backoff, "Retrying Atlas request"),
+            }
+            tokio::time::sleep(backoff).await;
+
+            request = retry;
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request with authorization. If Atlas answers with `401 Unauthorized` and the authenticator can
+    /// recover from it (refreshed token, new digest challenge), the request is sent once more.
+    async fn send_authorized(&self, endpoint: &str, mut request: Request) -> Result<Response> {
+        let retry = request.try_clone();
 
-        Ok(Self { client, access_token })
+        self.authenticator.authorize(&mut request).await?;
+        let response = self.execute(endpoint, request).await?;
+
+        match (response.status(), retry) {
+            (StatusCode::UNAUTHORIZED, Some(mut retry))
+                if self.authenticator.handle_unauthorized(&response).await? =>
+            {
+                self.authenticator.authorize(&mut retry).await?;
+                self.execute(endpoint, retry).await
+            }
+            _ => Ok(response),
+        }
     }
 
+    /// Executes a single HTTP request within the rate limit of its organization and records its outcome and latency
+    async fn execute(&self, endpoint: &str, request: Request) -> Result<Response> {
+        if let Some(org_id) = org_id(&request) {
+            self.client.rate_limiter.acquire(org_id).await;
+        }
+
+        let method = request.method().clone();
+        let start = Instant::now();
+        let response = self.client.http.execute(request).await;
+
+        let status = response.as_ref().ok().map(Response::status);
+        self.client
+            .metrics
+            .observe_atlas_request(&method, endpoint, status, start.elapsed());
+
+        Ok(response?)
+    }
+}
+
+#[async_trait]
+impl AtlasUserApi for AtlasUserRepository {
     /// Invites a new user to the Atlas organization
-    pub async fn invite_atlas_user(&self, org_id: &str, user: &UserRequest<'_>) -> Result<UserResponse> {
-        let url = format!("{}/orgs/{}/users", ATLAS_API_V2_BASE_URL, org_id);
+    async fn invite_atlas_user(&self, org_id: &str, user: &UserRequest<'_>) -> Result<UserResponse> {
+        let url = format!("{}/orgs/{}/users", self.client.api_url, org_id);
 
         let response = self
-            .client
-            .post(&url)
-            .bearer_auth(self.access_token.as_ref())
-            .json(user)
-            .send()
+            .send(ORG_USERS_ENDPOINT, self.client.http.post(&url).json(user))
             .await?;
 
         match response.status() {
@@ -57,33 +177,28 @@ impl AtlasUserRepository {
     }
 
     /// Updates an existing user in the Atlas organization
-    pub async fn update_atlas_user(&self, org_id: &str, user_id: &str, user: &UserRequest<'_>) -> Result<UserResponse> {
-        let url = format!("{}/orgs/{}/users/{}", ATLAS_API_V2_BASE_URL, org_id, user_id);
+    async fn update_atlas_user(&self, org_id: &str, user_id: &str, user: &UserRequest<'_>) -> Result<UserResponse> {
+        let url = format!("{}/orgs/{}/users/{}", self.client.api_url, org_id, user_id);
 
         let response = self
-            .client
-            .patch(&url)
-            .bearer_auth(self.access_token.as_ref())
-            .json(user)
-            .send()
+            .send(ORG_USER_ENDPOINT, self.client.http.patch(&url).json(user))
             .await?;
 
         match response.status() {
             StatusCode::OK => handle_ok_response(response).await,
+            StatusCode::NOT_FOUND => Err(Error::AtlasUserNotFound {
+                user_id: user_id.to_string(),
+                org_id: org_id.to_string(),
+            }),
             status => handle_error(status, response).await,
         }
     }
 
     /// Deletes a user from the Atlas organization
-    pub async fn delete_atlas_user_from_org(&self, org_id: &str, user_id: &str) -> Result<()> {
-        let url = format!("{}/orgs/{}/users/{}", ATLAS_API_V2_BASE_URL, org_id, user_id);
+    async fn delete_atlas_user_from_org(&self, org_id: &str, user_id: &str) -> Result<()> {
+        let url = format!("{}/orgs/{}/users/{}", self.client.api_url, org_id, user_id);
 
-        let response = self
-            .client
-            .delete(&url)
-            .bearer_auth(self.access_token.as_ref())
-            .send()
-            .await?;
+        let response = self.send(ORG_USER_ENDPOINT, self.client.http.delete(&url)).await?;
 
         match response.status() {
             StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
@@ -92,15 +207,10 @@ impl AtlasUserRepository {
     }
 
     /// Gets a user from the Atlas organization by user ID
-    pub async fn get_atlas_user(&self, org_id: &str, user_id: &str) -> Result<UserResponse> {
-        let url = format!("{}/orgs/{}/users/{}", ATLAS_API_V2_BASE_URL, org_id, user_id);
+    async fn get_atlas_user(&self, org_id: &str, user_id: &str) -> Result<UserResponse> {
+        let url = format!("{}/orgs/{}/users/{}", self.client.api_url, org_id, user_id);
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(self.access_token.as_ref())
-            .send()
-            .await?;
+        let response = self.send(ORG_USER_ENDPOINT, self.client.http.get(&url)).await?;
 
         match response.status() {
             StatusCode::OK => handle_ok_response(response).await,
@@ -113,31 +223,45 @@ impl AtlasUserRepository {
     }
 
     /// Finds a user in the Atlas organization by username (email)
-    pub async fn find_atlas_user_by_username(&self, org_id: &str, username: &str) -> Result<Option<UserResponse>> {
-        let url = format!("{}/orgs/{}/users", ATLAS_API_V2_BASE_URL, org_id);
+    async fn find_atlas_user_by_username(&self, org_id: &str, username: &str) -> Result<Option<UserResponse>> {
+        let url = format!("{}/orgs/{}/users", self.client.api_url, org_id);
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(self.access_token.as_ref())
-            .send()
+        let users: Vec<UserResponse> = self
+            .list_paginated(ORG_USERS_ENDPOINT, &url, &[("username", username)])
             .await?;
+        Ok(users.into_iter().find(|u| u.username.eq_ignore_ascii_case(username)))
+    }
+
+    /// Verifies the credentials with a cheap authenticated request listing at most one organization
+    async fn verify_credentials(&self) -> Result<()> {
+        let url = format!("{}/orgs", self.client.api_url);
+
+        let request = self.client.http.get(&url).query(&[("itemsPerPage", 1)]);
+        let response = self.send(ORGS_ENDPOINT, request).await?;
 
         match response.status() {
-            StatusCode::OK => {
-                let users: UsersListResponse = handle_ok_response(response).await?;
-                Ok(users.results.into_iter().find(|u| u.username == username))
-            }
+            StatusCode::OK => Ok(()),
             status => handle_error(status, response).await,
         }
     }
 }
 
-/// Response wrapper for listing users
+/// A single page of a paginated Atlas list response
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct UsersListResponse {
-    results: Vec<UserResponse>,
+struct PaginatedResponse<A> {
+    #[serde(default = "Vec::new")]
+    results: Vec<A>,
+    /// Total number of items across all pages (present when `includeCount=true`)
+    #[serde(default)]
+    total_count: Option<usize>,
+}
+
+/// Extracts the organization a request is scoped to from its `/orgs/{orgId}/...` path
+fn org_id(request: &Request) -> Option<&str> {
+    let mut segments = request.url().path_segments()?;
+    segments.find(|segment| *segment == "orgs")?;
+    segments.next().filter(|org_id| !org_id.is_empty())
 }
 
 async fn handle_ok_response<A>(response: Response) -> Result<A>
@@ -150,6 +274,6 @@ where
 }
 
 async fn handle_error<A>(status: StatusCode, response: Response) -> Result<A> {
-    let message = response.text().await?;
-    Err(Error::Api { status, message })
+    let body = response.text().await?;
+    Err(AtlasApiError::from_body(&body).into_error(status))
 }
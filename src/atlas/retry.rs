@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::Method;
+use reqwest::Response;
+use reqwest::StatusCode;
+
+use crate::atlas::error::Error;
+use crate::atlas::error::Result;
+use crate::config::RetryConfig;
+
+/// Decides whether and when a failed Atlas request is sent again.
+///
+/// Rate limited requests (`429`) and transient server or network failures are retried with jittered exponential
+/// backoff, or after the delay Atlas asks for via `Retry-After`. Requests that are not idempotent (POST invites)
+/// are only retried when Atlas cannot have processed them: after a `429` or a failed connection attempt.
+pub struct RetryPolicy {
+    config: RetryConfig,
+}
+
+impl RetryPolicy {
+    pub fn new(config: RetryConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns how long to wait before sending the request again after the given attempt, or `None` if the
+    /// outcome is final
+    pub fn backoff(&self, method: &Method, outcome: &Result<Response>, attempt: u32) -> Option<Duration> {
+        if attempt >= self.config.max_attempts {
+            return None;
+        }
+
+        let idempotent = method != Method::POST;
+        let retry_after = match outcome {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => retry_after(response),
+            Ok(response) if idempotent && is_transient(response.status()) => retry_after(response),
+            Ok(_) => return None,
+            Err(Error::Reqwest(e)) if e.is_connect() || (idempotent && e.is_timeout()) => None,
+            Err(_) => return None,
+        };
+
+        match retry_after {
+            // Waiting longer than a backoff would hold the reconciliation, it's requeued instead
+            Some(delay) if delay > self.config.max_backoff => None,
+            Some(delay) => Some(delay),
+            None => Some(self.exponential_backoff(attempt)),
+        }
+    }
+
+    /// Exponential backoff for the given attempt with equal jitter: half of the delay is fixed,
+    /// the other half random, so that concurrent reconciliations don't retry in lockstep
+    fn exponential_backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .config
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.config.max_backoff);
+        let half = delay / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+    }
+}
+
+/// Server errors that are usually gone by the next attempt
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Parses the `Retry-After` header, given either in seconds or as an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+            Some((date - Utc::now()).to_std().unwrap_or_default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::atlas::error::AtlasApiError;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(RetryConfig {
+            max_attempts: 4,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        })
+    }
+
+    fn response(status: StatusCode, retry_after: Option<&str>) -> Result<Response> {
+        let mut response = http::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            response = response.header(RETRY_AFTER, retry_after);
+        }
+        Ok(Response::from(response.body("").expect("valid response")))
+    }
+
+    /// Error of a request to a local port nobody listens on
+    async fn connect_error() -> Result<Response> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bindable local port");
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let error = reqwest::get(format!("http://{address}"))
+            .await
+            .expect_err("refused connection");
+        assert!(error.is_connect(), "{error:?}");
+        Err(Error::Reqwest(error))
+    }
+
+    /// Error of a request to a local port that accepts the connection but never responds
+    async fn timeout_error() -> Result<Response> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bindable local port");
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let _connection = listener.accept().await;
+            std::future::pending::<()>().await;
+        });
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(50))
+            .build()
+            .expect("HTTP client");
+        let error = client
+            .get(format!("http://{address}"))
+            .send()
+            .await
+            .expect_err("timed out request");
+        server.abort();
+        assert!(error.is_timeout(), "{error:?}");
+        Err(Error::Reqwest(error))
+    }
+
+    fn assert_exponential(backoff: Option<Duration>, delay: Duration) {
+        let backoff = backoff.expect("retried");
+        assert!(
+            delay / 2 <= backoff && backoff <= delay,
+            "{backoff:?} not within jitter of {delay:?}"
+        );
+    }
+
+    #[test]
+    fn rate_limited_requests_are_retried_with_exponential_backoff() {
+        let outcome = response(StatusCode::TOO_MANY_REQUESTS, None);
+
+        assert_exponential(policy().backoff(&Method::GET, &outcome, 1), Duration::from_secs(1));
+        assert_exponential(policy().backoff(&Method::POST, &outcome, 2), Duration::from_secs(2));
+        assert_exponential(policy().backoff(&Method::PATCH, &outcome, 3), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn exponential_backoff_is_capped_at_max_backoff() {
+        let policy = RetryPolicy::new(RetryConfig {
+            max_attempts: 10,
+            ..policy().config
+        });
+        let outcome = response(StatusCode::SERVICE_UNAVAILABLE, None);
+
+        assert_exponential(policy.backoff(&Method::GET, &outcome, 9), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn last_attempt_is_not_retried() {
+        let outcome = response(StatusCode::TOO_MANY_REQUESTS, None);
+
+        assert_eq!(policy().backoff(&Method::GET, &outcome, 4), None);
+    }
+
+    #[test]
+    fn transient_server_errors_are_only_retried_for_idempotent_requests() {
+        for status in [
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::GATEWAY_TIMEOUT,
+        ] {
+            let outcome = response(status, None);
+
+            assert_exponential(policy().backoff(&Method::GET, &outcome, 1), Duration::from_secs(1));
+            assert_exponential(policy().backoff(&Method::DELETE, &outcome, 1), Duration::from_secs(1));
+            assert_eq!(policy().backoff(&Method::POST, &outcome, 1), None, "{status}");
+        }
+    }
+
+    #[test]
+    fn other_responses_are_final() {
+        for status in [
+            StatusCode::OK,
+            StatusCode::BAD_REQUEST,
+            StatusCode::UNAUTHORIZED,
+            StatusCode::NOT_FOUND,
+            StatusCode::CONFLICT,
+            StatusCode::NOT_IMPLEMENTED,
+        ] {
+            let outcome = response(status, None);
+
+            assert_eq!(policy().backoff(&Method::GET, &outcome, 1), None, "{status}");
+        }
+    }
+
+    #[test]
+    fn retry_after_in_seconds_is_used_as_backoff() {
+        let outcome = response(StatusCode::TOO_MANY_REQUESTS, Some("7"));
+
+        assert_eq!(
+            policy().backoff(&Method::POST, &outcome, 1),
+            Some(Duration::from_secs(7))
+        );
+    }
+
+    #[test]
+    fn retry_after_http_date_is_used_as_backoff() {
+        let date = (Utc::now() + chrono::Duration::seconds(20)).format("%a, %d %b %Y %H:%M:%S GMT");
+        let outcome = response(StatusCode::SERVICE_UNAVAILABLE, Some(&date.to_string()));
+
+        let backoff = policy().backoff(&Method::GET, &outcome, 1).expect("retried");
+
+        assert!(
+            Duration::from_secs(18) <= backoff && backoff <= Duration::from_secs(20),
+            "{backoff:?}"
+        );
+    }
+
+    #[test]
+    fn retry_after_http_date_in_the_past_retries_immediately() {
+        let outcome = response(StatusCode::TOO_MANY_REQUESTS, Some("Sun, 06 Nov 1994 08:49:37 GMT"));
+
+        assert_eq!(policy().backoff(&Method::GET, &outcome, 1), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn invalid_retry_after_falls_back_to_exponential_backoff() {
+        let outcome = response(StatusCode::TOO_MANY_REQUESTS, Some("soon"));
+
+        assert_exponential(policy().backoff(&Method::GET, &outcome, 1), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn retry_after_beyond_max_backoff_is_not_retried() {
+        let seconds = response(StatusCode::TOO_MANY_REQUESTS, Some("31"));
+        let date = (Utc::now() + chrono::Duration::minutes(5)).format("%a, %d %b %Y %H:%M:%S GMT");
+        let http_date = response(StatusCode::TOO_MANY_REQUESTS, Some(&date.to_string()));
+
+        assert_eq!(policy().backoff(&Method::GET, &seconds, 1), None);
+        assert_eq!(policy().backoff(&Method::GET, &http_date, 1), None);
+    }
+
+    #[tokio::test]
+    async fn failed_connections_are_retried_for_all_requests() {
+        let outcome = connect_error().await;
+
+        assert_exponential(policy().backoff(&Method::GET, &outcome, 1), Duration::from_secs(1));
+        assert_exponential(policy().backoff(&Method::POST, &outcome, 1), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_only_retried_for_idempotent_requests() {
+        let outcome = timeout_error().await;
+
+        assert_exponential(policy().backoff(&Method::GET, &outcome, 1), Duration::from_secs(1));
+        assert_eq!(policy().backoff(&Method::POST, &outcome, 1), None);
+    }
+
+    #[test]
+    fn other_errors_are_final() {
+        let outcome = Err(Error::Api {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            error: AtlasApiError::default(),
+        });
+
+        assert_eq!(policy().backoff(&Method::GET, &outcome, 1), None);
+    }
+}
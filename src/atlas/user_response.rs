@@ -8,7 +8,7 @@ use crate::crd::AtlasUserRoles;
 use crate::crd::UserOrgMembershipStatus;
 
 /// Response from Atlas API for user operations
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UserResponse {
     /// The Atlas user ID
@@ -1,13 +1,85 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use clap::Args;
 use clap::Parser;
+use clap::Subcommand;
+use url::Url;
+
+use crate::atlas::auth::Credentials;
+use crate::config::AtlasApiConfig;
+use crate::webhook::WebhookTls;
 
 /// MongoDB Atlas Kubernetes Operator
 #[derive(Parser)]
 pub struct Cli {
-    /// OAuth access token for Atlas API authentication
-    #[clap(long, env = "ATLAS_ACCESS_TOKEN")]
-    pub access_token: Arc<str>,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Runs the operator
+    Run(Box<RunArgs>),
+    /// Manages the AtlasUser CustomResourceDefinition
+    Crd {
+        #[clap(subcommand)]
+        command: CrdCommand,
+    },
+    /// Manages the validating admission webhook
+    Webhook {
+        #[clap(subcommand)]
+        command: WebhookCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CrdCommand {
+    /// Prints the CustomResourceDefinition as YAML
+    Print,
+    /// Server-side applies the CustomResourceDefinition to the cluster
+    Install,
+}
+
+#[derive(Subcommand)]
+pub enum WebhookCommand {
+    /// Prints the ValidatingWebhookConfiguration as YAML
+    Print(WebhookServiceArgs),
+}
+
+/// The Service in front of the admission webhook
+#[derive(Args)]
+pub struct WebhookServiceArgs {
+    /// Name of the Service
+    #[clap(long, default_value = "mongodb-atlas-k8s-operator")]
+    pub service_name: String,
+
+    /// Namespace of the Service
+    #[clap(long, default_value = "default")]
+    pub service_namespace: String,
+
+    /// Port of the Service
+    #[clap(long, default_value_t = 443)]
+    pub service_port: i32,
+
+    /// PEM file with the CA certificates that signed the webhook certificate. Leave it out if the CA bundle is
+    /// injected, e.g. by cert-manager.
+    #[clap(long)]
+    pub ca_bundle: Option<PathBuf>,
+}
+
+/// Arguments of the `run` command
+#[derive(Args)]
+pub struct RunArgs {
+    #[clap(flatten)]
+    pub credentials: AtlasCredentials,
+
+    #[clap(flatten)]
+    pub atlas_api: AtlasApiArgs,
+
+    #[clap(flatten)]
+    pub webhook: WebhookArgs,
 
     /// Path to configuration file
     #[clap(long, short, env = "CONFIG_PATH")]
@@ -16,4 +88,147 @@ pub struct Cli {
     /// Namespaces to watch (can be specified multiple times)
     #[clap(long, short, default_value = "default")]
     pub namespaces: Vec<String>,
+
+    /// Address to serve the Prometheus metrics endpoint on
+    #[clap(long, env = "METRICS_ADDRESS", default_value = "0.0.0.0:8080")]
+    pub metrics_address: SocketAddr,
+
+    /// Address to serve the liveness (`/healthz`) and readiness (`/readyz`) probes on
+    #[clap(long, env = "PROBE_ADDRESS", default_value = "0.0.0.0:8081")]
+    pub probe_address: SocketAddr,
+
+    /// Server-side applies the CustomResourceDefinition at startup, before reconciling
+    #[clap(long, env = "INSTALL_CRD")]
+    pub install_crd: bool,
+}
+
+/// Settings of the validating admission webhook, served over HTTPS if a certificate is given
+#[derive(Args)]
+pub struct WebhookArgs {
+    /// Address to serve the validating admission webhook on
+    #[clap(long, env = "WEBHOOK_ADDRESS", default_value = "0.0.0.0:8443")]
+    pub webhook_address: SocketAddr,
+
+    /// PEM file with the certificate chain of the webhook. The webhook is only served if this is set.
+    #[clap(long, env = "WEBHOOK_CERT", requires = "webhook_key")]
+    pub webhook_cert: Option<PathBuf>,
+
+    /// PEM file with the private key of the webhook certificate
+    #[clap(long, env = "WEBHOOK_KEY", requires = "webhook_cert")]
+    pub webhook_key: Option<PathBuf>,
+}
+
+impl WebhookArgs {
+    /// The certificate and key to serve the webhook with, if configured
+    pub fn tls(&self) -> Option<WebhookTls> {
+        match (&self.webhook_cert, &self.webhook_key) {
+            (Some(cert_path), Some(key_path)) => Some(WebhookTls {
+                cert_path: cert_path.clone(),
+                key_path: key_path.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Overrides of the Atlas API client settings from the configuration file
+#[derive(Args)]
+pub struct AtlasApiArgs {
+    /// Base URL of the Atlas deployment, e.g. `https://cloud.mongodbgov.com` or a local mock server
+    #[clap(long, env = "ATLAS_BASE_URL")]
+    pub atlas_base_url: Option<Url>,
+
+    /// Version of the Atlas Admin API v2, e.g. `2025-02-19`
+    #[clap(long, env = "ATLAS_API_VERSION")]
+    pub atlas_api_version: Option<String>,
+
+    /// Proxy for all requests to Atlas
+    #[clap(long, env = "ATLAS_PROXY")]
+    pub atlas_proxy: Option<Url>,
+
+    /// PEM file with additional CA certificates to trust for requests to Atlas
+    #[clap(long, env = "ATLAS_CA_BUNDLE")]
+    pub atlas_ca_bundle: Option<PathBuf>,
+}
+
+impl AtlasApiArgs {
+    /// Applies the given flags on top of the configuration file
+    pub fn apply(self, config: &mut AtlasApiConfig) {
+        if let Some(base_url) = self.atlas_base_url {
+            config.base_url = base_url;
+        }
+        if let Some(api_version) = self.atlas_api_version {
+            config.api_version = api_version;
+        }
+        if let Some(proxy) = self.atlas_proxy {
+            config.proxy = Some(proxy);
+        }
+        if let Some(ca_bundle) = self.atlas_ca_bundle {
+            config.ca_bundle = Some(ca_bundle);
+        }
+    }
+}
+
+/// Default credentials for Atlas API authentication: a static access token, service account credentials
+/// or a programmatic API key pair. Optional if every AtlasUser references a connection Secret.
+#[derive(Args)]
+#[group(required = false, multiple = true)]
+pub struct AtlasCredentials {
+    /// OAuth access token for Atlas API authentication
+    #[clap(
+        long,
+        env = "ATLAS_ACCESS_TOKEN",
+        conflicts_with_all = ["client_id", "client_secret", "public_key", "private_key"]
+    )]
+    pub access_token: Option<Arc<str>>,
+
+    /// Client ID of the Atlas service account
+    #[clap(
+        long,
+        env = "ATLAS_CLIENT_ID",
+        requires = "client_secret",
+        conflicts_with_all = ["public_key", "private_key"]
+    )]
+    pub client_id: Option<Arc<str>>,
+
+    /// Client secret of the Atlas service account
+    #[clap(long, env = "ATLAS_CLIENT_SECRET", requires = "client_id")]
+    pub client_secret: Option<Arc<str>>,
+
+    /// Public key of the Atlas programmatic API key (HTTP Digest authentication)
+    #[clap(long, env = "ATLAS_PUBLIC_KEY", requires = "private_key")]
+    pub public_key: Option<Arc<str>>,
+
+    /// Private key of the Atlas programmatic API key (HTTP Digest authentication)
+    #[clap(long, env = "ATLAS_PRIVATE_KEY", requires = "public_key")]
+    pub private_key: Option<Arc<str>>,
+}
+
+impl AtlasCredentials {
+    /// Converts the given flags into Atlas credentials, if a complete set was provided
+    pub fn into_credentials(self) -> Option<Credentials> {
+        match self {
+            AtlasCredentials {
+                access_token: Some(access_token),
+                ..
+            } => Some(Credentials::AccessToken(access_token)),
+            AtlasCredentials {
+                client_id: Some(client_id),
+                client_secret: Some(client_secret),
+                ..
+            } => Some(Credentials::ServiceAccount {
+                client_id,
+                client_secret,
+            }),
+            AtlasCredentials {
+                public_key: Some(public_key),
+                private_key: Some(private_key),
+                ..
+            } => Some(Credentials::ApiKey {
+                public_key,
+                private_key,
+            }),
+            _ => None,
+        }
+    }
 }
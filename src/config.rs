@@ -1,13 +1,24 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::path::PathBuf;
 use std::time::Duration;
 
+use chrono::NaiveDate;
 use serde::Deserialize;
+use url::Url;
+
+use crate::crd::AdoptionPolicy;
+use crate::crd::ExternalDeletionPolicy;
 
 /// Configuration for the operator
 #[derive(Debug, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
     pub atlas_user: AtlasUserConfig,
+    #[serde(default)]
+    pub atlas_api: AtlasApiConfig,
+    #[serde(default)]
+    pub leader_election: LeaderElectionConfig,
 }
 
 /// Configuration specific to AtlasUser reconciliation
@@ -16,8 +27,28 @@ pub struct AtlasUserConfig {
     /// How long to wait before requeuing a reconciliation
     #[serde(with = "humantime_serde")]
     pub requeue_duration: Duration,
-    /// Whether it's safe to delete users from Atlas when the K8s resource is deleted
+    /// Whether it's safe to delete users from Atlas when the K8s resource is deleted, unless the AtlasUser sets its
+    /// own deletion policy
     pub safe_to_delete: bool,
+    /// What to do when the roles or teams of a user in Atlas differ from the spec
+    #[serde(default)]
+    pub drift_policy: DriftPolicy,
+    /// What to do when the `username` or `orgId` of an AtlasUser changes after the user was invited
+    #[serde(default)]
+    pub identity_change_policy: IdentityChangePolicy,
+    /// How often a user whose invitation expired is invited again before the operator gives up, 0 to never
+    #[serde(default = "default_max_reinvitations")]
+    pub max_reinvitations: u32,
+    /// What to do when a user is removed from its organization outside the operator, unless the AtlasUser sets it
+    #[serde(default)]
+    pub on_external_deletion: ExternalDeletionPolicy,
+    /// Whether users that already are members of the organization are taken over, unless the AtlasUser sets it
+    #[serde(default)]
+    pub adoption_policy: AdoptionPolicy,
+}
+
+fn default_max_reinvitations() -> u32 {
+    3
 }
 
 impl Default for AtlasUserConfig {
@@ -25,10 +56,240 @@ impl Default for AtlasUserConfig {
         Self {
             requeue_duration: Duration::from_secs(60),
             safe_to_delete: false,
+            drift_policy: DriftPolicy::default(),
+            identity_change_policy: IdentityChangePolicy::default(),
+            max_reinvitations: default_max_reinvitations(),
+            on_external_deletion: ExternalDeletionPolicy::default(),
+            adoption_policy: AdoptionPolicy::default(),
+        }
+    }
+}
+
+/// Configuration of the client for the Atlas Admin API
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AtlasApiConfig {
+    /// Base URL of the Atlas deployment, e.g. `https://cloud.mongodbgov.com` for Atlas for Government
+    pub base_url: Url,
+    /// Version of the Atlas Admin API v2, sent in the versioned `Accept` header
+    pub api_version: String,
+    /// Timeout for establishing a connection to Atlas
+    #[serde(with = "humantime_serde")]
+    pub connect_timeout: Duration,
+    /// Timeout for a single request to Atlas, from connecting until the response body was read
+    #[serde(with = "humantime_serde")]
+    pub request_timeout: Duration,
+    /// Proxy for all requests to Atlas
+    pub proxy: Option<Url>,
+    /// PEM file with additional CA certificates to trust, e.g. for a TLS-intercepting proxy
+    pub ca_bundle: Option<PathBuf>,
+    pub retry: RetryConfig,
+    pub rate_limit: RateLimitConfig,
+}
+
+impl Default for AtlasApiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: Url::parse("https://cloud.mongodb.com").expect("valid default Atlas base URL"),
+            api_version: "2025-02-19".to_string(),
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(30),
+            proxy: None,
+            ca_bundle: None,
+            retry: RetryConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
+}
+
+impl AtlasApiConfig {
+    /// Checks the URLs, the API version and the rate limits, so that misconfigurations fail at startup
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let base_url = &self.base_url;
+        if !matches!(base_url.scheme(), "https" | "http") || base_url.host().is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "Atlas base URL {base_url} must be an absolute http(s) URL"
+            )));
+        }
+        if base_url.query().is_some() || base_url.fragment().is_some() {
+            return Err(ConfigError::Invalid(format!(
+                "Atlas base URL {base_url} must not have a query or fragment"
+            )));
+        }
+
+        if NaiveDate::parse_from_str(&self.api_version, "%Y-%m-%d").is_err() {
+            return Err(ConfigError::Invalid(format!(
+                "Atlas API version {} must be a date like 2025-02-19",
+                self.api_version
+            )));
+        }
+
+        if let Some(proxy) = self.proxy.as_ref() {
+            if !matches!(proxy.scheme(), "http" | "https") || proxy.host().is_none() {
+                return Err(ConfigError::Invalid(format!(
+                    "Atlas proxy URL {proxy} must be an absolute http(s) URL"
+                )));
+            }
+        }
+
+        if self.rate_limit.default.requests_per_minute == 0 {
+            return Err(ConfigError::Invalid(
+                "Atlas rate limit requests_per_minute must be at least 1".to_string(),
+            ));
+        }
+        let unlimited_org = self
+            .rate_limit
+            .orgs
+            .iter()
+            .find(|(_, budget)| budget.requests_per_minute == 0);
+        if let Some((org_id, _)) = unlimited_org {
+            return Err(ConfigError::Invalid(format!(
+                "Atlas rate limit requests_per_minute of organization {org_id} must be at least 1"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Retries of rate limited and transiently failing Atlas requests
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Maximum number of attempts per request, including the first one
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for every further retry
+    #[serde(with = "humantime_serde")]
+    pub initial_backoff: Duration,
+    /// Upper bound of the backoff. Requests whose `Retry-After` exceeds it are not retried.
+    #[serde(with = "humantime_serde")]
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Client-side rate limits of Atlas requests per organization
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RateLimitConfig {
+    /// Budget of every organization without an override
+    #[serde(flatten)]
+    pub default: RateLimitBudget,
+    /// Budgets of specific organizations, keyed by org ID
+    #[serde(default)]
+    pub orgs: HashMap<String, RateLimitBudget>,
+}
+
+/// Request budget of a single organization
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitBudget {
+    /// Sustained number of requests per minute, at least 1
+    pub requests_per_minute: u32,
+    /// Number of requests that may be sent at once before the sustained rate applies
+    pub burst: u32,
+}
+
+impl Default for RateLimitBudget {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 100,
+            burst: 10,
         }
     }
 }
 
+/// Handling of drift between an AtlasUser spec and the user in Atlas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftPolicy {
+    /// Re-apply the spec to Atlas
+    #[default]
+    Correct,
+    /// Only record the drift in the status
+    ReportOnly,
+}
+
+/// Handling of changes to the `username` or `orgId` of an AtlasUser, which identify the user in Atlas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityChangePolicy {
+    /// Refuse the change: the admission webhook denies it and reconciliation fails until it is reverted
+    #[default]
+    Reject,
+    /// Invite the user with the new identity, then remove the previous membership if the deletion policy of the
+    /// AtlasUser is `Delete`
+    Migrate,
+}
+
+/// Configuration of the Lease-based leader election between replicas of the operator
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LeaderElectionConfig {
+    /// Whether only the replica holding the lease reconciles AtlasUsers
+    pub enabled: bool,
+    /// Name of the Lease object
+    pub lease_name: String,
+    /// Namespace of the Lease object, defaults to the namespace of the operator pod
+    pub lease_namespace: Option<String>,
+    /// How long standby replicas wait after the last renewal before taking over the lease
+    #[serde(with = "humantime_serde")]
+    pub lease_duration: Duration,
+    /// How long the leader keeps retrying to renew the lease before it steps down
+    #[serde(with = "humantime_serde")]
+    pub renew_deadline: Duration,
+    /// How long to wait between attempts to acquire or renew the lease
+    #[serde(with = "humantime_serde")]
+    pub retry_period: Duration,
+}
+
+impl Default for LeaderElectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lease_name: "mongodb-atlas-k8s-operator".to_string(),
+            lease_namespace: None,
+            lease_duration: Duration::from_secs(15),
+            renew_deadline: Duration::from_secs(10),
+            retry_period: Duration::from_secs(2),
+        }
+    }
+}
+
+impl LeaderElectionConfig {
+    /// Checks that the leader steps down before standby replicas may take over the lease, so that two replicas
+    /// never reconcile at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.retry_period.is_zero() {
+            return Err(ConfigError::Invalid(
+                "Leader election retry_period must not be zero".to_string(),
+            ));
+        }
+        if self.retry_period >= self.renew_deadline || self.renew_deadline >= self.lease_duration {
+            return Err(ConfigError::Invalid(format!(
+                "Leader election requires retry_period ({:?}) < renew_deadline ({:?}) < lease_duration ({:?})",
+                self.retry_period, self.renew_deadline, self.lease_duration
+            )));
+        }
+        if self.lease_duration.subsec_nanos() != 0 || i32::try_from(self.lease_duration.as_secs()).is_err() {
+            return Err(ConfigError::Invalid(format!(
+                "Leader election lease_duration ({:?}) must be a whole number of seconds up to {}",
+                self.lease_duration,
+                i32::MAX
+            )));
+        }
+
+        Ok(())
+    }
+}
+
 impl Config {
     /// Loads configuration from a YAML file
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
@@ -44,4 +305,138 @@ pub enum ConfigError {
     Io(#[from] std::io::Error),
     #[error("Failed to parse config file: {0}")]
     Parse(#[from] serde_yaml::Error),
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_url(base_url: &str) -> AtlasApiConfig {
+        AtlasApiConfig {
+            base_url: Url::parse(base_url).expect("valid URL"),
+            ..AtlasApiConfig::default()
+        }
+    }
+
+    fn api_version(api_version: &str) -> AtlasApiConfig {
+        AtlasApiConfig {
+            api_version: api_version.to_string(),
+            ..AtlasApiConfig::default()
+        }
+    }
+
+    fn proxy(proxy: &str) -> AtlasApiConfig {
+        AtlasApiConfig {
+            proxy: Some(Url::parse(proxy).expect("valid URL")),
+            ..AtlasApiConfig::default()
+        }
+    }
+
+    fn requests_per_minute(requests_per_minute: u32) -> AtlasApiConfig {
+        let mut config = AtlasApiConfig::default();
+        config.rate_limit.default.requests_per_minute = requests_per_minute;
+        config
+    }
+
+    #[test]
+    fn atlas_api_config_validation() {
+        let cases = [
+            // Base URL scheme and host
+            (base_url("http://localhost:8080"), true),
+            (base_url("ftp://cloud.mongodb.com"), false),
+            (base_url("file:///atlas"), false),
+            // Base URL query and fragment
+            (base_url("https://atlas.internal/proxy/"), true),
+            (base_url("https://cloud.mongodb.com/?env=dev"), false),
+            (base_url("https://cloud.mongodb.com/#v2"), false),
+            // API version date
+            (api_version("2024-08-05"), true),
+            (api_version("v2"), false),
+            (api_version("2025-02-30"), false),
+            // Proxy URL
+            (proxy("http://proxy.internal:3128"), true),
+            (proxy("socks5://proxy.internal:1080"), false),
+            (proxy("unix:/run/proxy.sock"), false),
+            // Rate limit
+            (requests_per_minute(1), true),
+            (requests_per_minute(0), false),
+        ];
+
+        for (config, valid) in cases {
+            assert_eq!(config.validate().is_ok(), valid, "{config:?}");
+        }
+    }
+
+    fn leader_election(
+        lease_duration: Duration,
+        renew_deadline: Duration,
+        retry_period: Duration,
+    ) -> LeaderElectionConfig {
+        LeaderElectionConfig {
+            enabled: true,
+            lease_duration,
+            renew_deadline,
+            retry_period,
+            ..LeaderElectionConfig::default()
+        }
+    }
+
+    #[test]
+    fn leader_election_accepts_retry_below_renew_below_lease() {
+        let config = leader_election(Duration::from_secs(15), Duration::from_secs(10), Duration::from_secs(2));
+
+        assert!(config.validate().is_ok());
+        assert!(LeaderElectionConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn leader_election_rejects_zero_retry_period() {
+        let config = leader_election(Duration::from_secs(15), Duration::from_secs(10), Duration::ZERO);
+
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn leader_election_rejects_unordered_durations() {
+        let secs = Duration::from_secs;
+        let unordered = [
+            // retry_period == renew_deadline
+            leader_election(secs(15), secs(10), secs(10)),
+            // retry_period > renew_deadline
+            leader_election(secs(15), secs(10), secs(12)),
+            // renew_deadline == lease_duration
+            leader_election(secs(15), secs(15), secs(2)),
+            // renew_deadline > lease_duration
+            leader_election(secs(15), secs(20), secs(2)),
+        ];
+
+        for config in unordered {
+            assert!(config.validate().is_err(), "{config:?}");
+        }
+    }
+
+    #[test]
+    fn leader_election_rejects_lease_duration_not_fitting_lease() {
+        let fractional = leader_election(
+            Duration::from_millis(15_500),
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+        );
+        let too_long = leader_election(
+            Duration::from_secs(i32::MAX as u64 + 1),
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+        );
+        let longest = leader_election(
+            Duration::from_secs(i32::MAX as u64),
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+        );
+
+        assert!(fractional.validate().is_err());
+        assert!(too_long.validate().is_err());
+        assert!(longest.validate().is_ok());
+    }
 }
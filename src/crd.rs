@@ -1,10 +1,17 @@
+use std::fmt;
 use std::sync::Arc;
 
+use chrono::DateTime;
+use chrono::Utc;
 use kube::CustomResource;
 use kuberator::ObserveGeneration;
 use schemars::JsonSchema;
+use schemars::Schema;
+use schemars::SchemaGenerator;
 use serde::Deserialize;
 use serde::Serialize;
+use strum::Display;
+use strum::EnumString;
 
 /// An `AtlasUser` struct is generated by the `CustomResource` derive macro.
 /// This struct represents the spec part of the custom resource definition (CRD) for the `AtlasUser` resource.
@@ -16,6 +23,12 @@ use serde::Serialize;
     plural = "atlasusers",
     derive = "PartialEq",
     status = "AtlasUserStatus",
+    shortname = "atlasuser",
+    shortname = "au",
+    printcolumn = r#"{"name": "Username", "type": "string", "jsonPath": ".spec.username"}"#,
+    printcolumn = r#"{"name": "Status", "type": "string", "jsonPath": ".status.membershipStatus"}"#,
+    printcolumn = r#"{"name": "Ready", "type": "string", "jsonPath": ".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name": "Age", "type": "date", "jsonPath": ".metadata.creationTimestamp"}"#,
     namespaced
 )]
 #[serde(rename_all = "camelCase")]
@@ -23,12 +36,101 @@ pub struct AtlasUserSpec {
     /// The MongoDB Atlas organization ID
     pub org_id: String,
     /// The email address of the user to invite/manage
+    #[schemars(email)]
     pub username: String,
     /// The roles to assign to the user
     pub roles: AtlasUserRoles,
     /// The team IDs to assign the user to
     #[serde(default)]
     pub team_ids: Vec<String>,
+    /// Secret in the same namespace holding the Atlas credentials for this organization.
+    /// If not set, the operator-wide credentials are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_secret_ref: Option<SecretReference>,
+    /// What to do when the user is removed from the organization outside the operator.
+    /// If not set, the operator-wide policy is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "nullable::<ExternalDeletionPolicy>")]
+    pub on_external_deletion: Option<ExternalDeletionPolicy>,
+    /// What to do with the user in Atlas when the resource is deleted. Overridden by the
+    /// `atlasusers.moertel.com/deletion-policy` annotation. If not set, the user is deleted if the operator-wide
+    /// `safe_to_delete` is set and retained otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "nullable::<DeletionPolicy>")]
+    pub deletion_policy: Option<DeletionPolicy>,
+    /// Whether a user that already is a member of the organization is taken over instead of invited.
+    /// If not set, the operator-wide policy is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "nullable::<AdoptionPolicy>")]
+    pub adoption_policy: Option<AdoptionPolicy>,
+}
+
+/// Handling of a user that already is a member of the organization when an AtlasUser is reconciled the first time
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum AdoptionPolicy {
+    /// Take over the user and apply the spec to it
+    #[default]
+    #[serde(alias = "adopt")]
+    Adopt,
+    /// Take over the user only if its roles and teams already match the spec
+    #[serde(alias = "adopt_if_roles_match")]
+    AdoptIfRolesMatch,
+    /// Never take over an existing user
+    #[serde(alias = "never")]
+    Never,
+}
+
+/// How the operator came to manage the user in Atlas
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema)]
+pub enum UserOrigin {
+    /// The operator invited the user
+    Created,
+    /// The user already was a member of the organization and was taken over
+    Adopted,
+}
+
+/// Annotation overriding the deletion policy of an AtlasUser
+pub const DELETION_POLICY_ANNOTATION: &str = "atlasusers.moertel.com/deletion-policy";
+
+/// Handling of the user in Atlas when its AtlasUser is deleted
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Display, EnumString)]
+pub enum DeletionPolicy {
+    /// Remove the user from the organization
+    Delete,
+    /// Keep the user in the organization unchanged
+    Retain,
+    /// Keep the user in the organization, removing only the project roles granted by this AtlasUser
+    RemoveProjectRolesOnly,
+}
+
+/// Handling of a user that was removed from the organization outside the operator
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum ExternalDeletionPolicy {
+    /// Invite the user again
+    #[serde(alias = "recreate")]
+    Recreate,
+    /// Stop managing the user and leave it removed
+    #[serde(alias = "orphan")]
+    Orphan,
+    /// Report an error and leave the user removed until the policy changes
+    #[default]
+    #[serde(alias = "fail")]
+    Fail,
+}
+
+/// Schema of an optional enum as the nullable enum itself, as Kubernetes rejects the `anyOf` generated for it
+fn nullable<T: JsonSchema>(generator: &mut SchemaGenerator) -> Schema {
+    let mut schema = T::json_schema(generator);
+    schema.insert("nullable".to_string(), true.into());
+    schema
+}
+
+/// Reference to a Secret in the namespace of the resource
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretReference {
+    /// The name of the Secret
+    pub name: String,
 }
 
 /// Role assignments for an Atlas user
@@ -54,7 +156,7 @@ pub struct GroupRoleAssignment {
 }
 
 /// Organization-level role names
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, JsonSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OrgRoleName {
     OrgOwner,
@@ -66,7 +168,7 @@ pub enum OrgRoleName {
 }
 
 /// Group (project) level role names
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, JsonSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum GroupRoleName {
     GroupClusterManager,
@@ -83,8 +185,9 @@ pub enum GroupRoleName {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct AtlasUserStatus {
-    /// The Atlas user ID (set after invitation/creation)
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The Atlas user ID (set after invitation/creation).
+    /// Serialized as `null` once the user is gone, so that a status merge patch clears it.
+    #[serde(default)]
     pub user_id: Option<Arc<str>>,
     /// The membership status in the organization
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -92,9 +195,154 @@ pub struct AtlasUserStatus {
     /// The observed generation of the resource
     #[serde(skip_serializing_if = "Option::is_none")]
     pub observed_generation: Option<i64>,
-    /// Error message if reconciliation failed
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Error message if reconciliation failed.
+    /// Serialized as `null` when there is no error, so that a status merge patch clears it.
+    #[serde(default)]
     pub error: Option<String>,
+    /// Spec fields that differed from the user in Atlas during the last status sync.
+    /// Serialized as `null` when there is no drift, so that a status merge patch clears it.
+    #[serde(default)]
+    pub drift: Option<Vec<String>>,
+    /// The latest observations of the resource's state
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// When the pending invitation of the user was created.
+    /// Serialized as `null` once the invitation was accepted, so that a status merge patch clears it.
+    #[serde(default)]
+    pub invitation_created_at: Option<DateTime<Utc>>,
+    /// When the pending invitation of the user expires.
+    /// Serialized as `null` once the invitation was accepted, so that a status merge patch clears it.
+    #[serde(default)]
+    pub invitation_expires_at: Option<DateTime<Utc>>,
+    /// How often the user was invited again after an invitation expired
+    #[serde(default)]
+    pub reinvitations: u32,
+    /// Whether the expired invitation of the user was removed and the user is yet to be invited again
+    #[serde(default)]
+    pub reinvitation_pending: bool,
+    /// When the operator found the user removed from the organization outside the operator and did not invite it
+    /// again. Serialized as `null` once the user is managed again, so that a status merge patch clears it.
+    #[serde(default)]
+    pub deleted_externally_at: Option<DateTime<Utc>>,
+    /// Whether the operator invited the user or took over an existing member of the organization.
+    /// Serialized as `null` once the user is gone, so that a status merge patch clears it.
+    #[serde(default)]
+    #[schemars(schema_with = "nullable::<UserOrigin>")]
+    pub origin: Option<UserOrigin>,
+    /// The organization and username the user in Atlas was last invited or found with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applied_identity: Option<AppliedIdentity>,
+    /// The membership of the user under its identity before a migration, yet to be removed from Atlas.
+    /// Serialized as `null` once it was removed, so that a status merge patch clears it.
+    #[serde(default)]
+    pub previous_membership: Option<PreviousMembership>,
+}
+
+/// A membership of the user in Atlas under an identity it was migrated away from
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviousMembership {
+    /// The MongoDB Atlas organization ID
+    pub org_id: String,
+    /// The Atlas user ID in that organization
+    pub user_id: String,
+    /// The connection Secret with the credentials for that organization, `null` for the operator-wide credentials
+    #[serde(default)]
+    pub connection_secret_ref: Option<SecretReference>,
+}
+
+/// The identity of a user in Atlas: its organization and username
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedIdentity {
+    /// The MongoDB Atlas organization ID
+    pub org_id: String,
+    /// The email address of the user
+    pub username: String,
+    /// The connection Secret whose credentials applied the identity, `null` for the operator-wide credentials.
+    /// The user is removed from the organization with them, even if the spec references another Secret by then.
+    #[serde(default)]
+    pub connection_secret_ref: Option<SecretReference>,
+}
+
+impl AppliedIdentity {
+    /// The identity the spec asks for
+    pub fn of(spec: &AtlasUserSpec) -> Self {
+        Self {
+            org_id: spec.org_id.clone(),
+            username: spec.username.clone(),
+            connection_secret_ref: spec.connection_secret_ref.clone(),
+        }
+    }
+
+    /// Whether both identities refer to the same Atlas user, as usernames are case-insensitive
+    pub fn is_same(&self, other: &AppliedIdentity) -> bool {
+        self.org_id == other.org_id && self.username.eq_ignore_ascii_case(&other.username)
+    }
+}
+
+impl fmt::Display for AppliedIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in organization {}", self.username, self.org_id)
+    }
+}
+
+impl AtlasUserRoles {
+    /// These roles without the project roles granted by the given assignments. Assignments left without roles are
+    /// dropped, roles in other projects and organization roles are kept.
+    pub fn without_group_roles(&self, granted: &[GroupRoleAssignment]) -> AtlasUserRoles {
+        let group_role_assignments = self
+            .group_role_assignments
+            .iter()
+            .filter_map(|assignment| {
+                let granted_roles = granted
+                    .iter()
+                    .filter(|granted| granted.group_id.eq_ignore_ascii_case(&assignment.group_id))
+                    .flat_map(|granted| &granted.group_roles)
+                    .collect::<Vec<_>>();
+                let group_roles = assignment
+                    .group_roles
+                    .iter()
+                    .filter(|role| !granted_roles.contains(role))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                (!group_roles.is_empty()).then(|| GroupRoleAssignment {
+                    group_id: assignment.group_id.clone(),
+                    group_roles,
+                })
+            })
+            .collect();
+
+        AtlasUserRoles {
+            group_role_assignments,
+            org_roles: self.org_roles.clone(),
+        }
+    }
+}
+
+impl AtlasUserStatus {
+    /// Sets the condition of the given type, keeping the last transition time if its status did not change
+    pub fn set_condition(
+        &mut self,
+        type_: ConditionType,
+        status: bool,
+        reason: &str,
+        message: impl Into<String>,
+        observed_generation: Option<i64>,
+    ) {
+        let condition = Condition::new(type_, status, reason, message, observed_generation);
+        match self.conditions.iter_mut().find(|c| c.type_ == condition.type_) {
+            Some(existing) if existing.status == condition.status => {
+                *existing = Condition {
+                    last_transition_time: existing.last_transition_time,
+                    ..condition
+                };
+            }
+            Some(existing) => *existing = condition,
+            None => self.conditions.push(condition),
+        }
+    }
 }
 
 impl ObserveGeneration for AtlasUserStatus {
@@ -103,6 +351,73 @@ impl ObserveGeneration for AtlasUserStatus {
     }
 }
 
+/// A condition of the AtlasUser resource, following the Kubernetes API conventions
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    /// The type of the condition
+    #[serde(rename = "type")]
+    pub type_: ConditionType,
+    /// Whether the condition applies
+    pub status: ConditionStatus,
+    /// A machine-readable, CamelCase reason for the last transition
+    pub reason: String,
+    /// A human-readable message with details about the last transition
+    pub message: String,
+    /// When the condition last changed its status
+    pub last_transition_time: DateTime<Utc>,
+    /// The generation of the resource the condition was set for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+}
+
+impl Condition {
+    fn new(
+        type_: ConditionType,
+        status: bool,
+        reason: &str,
+        message: impl Into<String>,
+        observed_generation: Option<i64>,
+    ) -> Self {
+        Self {
+            type_,
+            status: if status {
+                ConditionStatus::True
+            } else {
+                ConditionStatus::False
+            },
+            reason: reason.to_string(),
+            message: message.into(),
+            last_transition_time: Utc::now(),
+            observed_generation,
+        }
+    }
+}
+
+/// The types of conditions reported on an AtlasUser
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema)]
+pub enum ConditionType {
+    /// The user is an active member of the organization with the roles and teams from the spec
+    Ready,
+    /// The user was invited to or already is a member of the organization
+    Invited,
+    /// The spec was successfully applied to or synced from Atlas
+    Synced,
+    /// The roles or teams of the user in Atlas differ from the spec
+    Drifted,
+    /// The last reconciliation failed
+    Error,
+    /// An older AtlasUser manages the same user, so this one is not reconciled
+    Conflict,
+}
+
+/// The status of a condition
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema)]
+pub enum ConditionStatus {
+    True,
+    False,
+}
+
 /// The membership status of a user in an Atlas organization
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -15,4 +15,20 @@ pub enum Error {
     Atlas(#[from] atlas::error::Error),
     #[error("Configuration error: {0}")]
     Config(#[from] config::ConfigError),
+    #[error("Metrics error: {0}")]
+    Metrics(#[from] prometheus::Error),
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Waiting for Kubernetes failed: {0}")]
+    Wait(#[from] kube::runtime::wait::Error),
+    #[error("CustomResourceDefinition not established within {0:?}")]
+    CrdNotEstablished(std::time::Duration),
+    #[error("TLS error: {0}")]
+    Tls(#[from] rustls::Error),
+    #[error("PEM error: {0}")]
+    Pem(#[from] rustls::pki_types::pem::Error),
+    #[error("Lost leadership to another replica")]
+    LeadershipLost,
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
 }
@@ -0,0 +1,158 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// How long reconciliations may be in flight without any progress before the operator is considered stuck
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Health state of the operator, backing the liveness and readiness probes
+pub struct Health {
+    watcher_ready: AtomicBool,
+    standby: AtomicBool,
+    credentials_verified: AtomicBool,
+    in_flight: AtomicUsize,
+    last_progress: Mutex<Instant>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            watcher_ready: AtomicBool::new(false),
+            standby: AtomicBool::new(false),
+            credentials_verified: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            last_progress: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl Health {
+    /// Marks the initial list of the AtlasUser watcher as completed
+    pub fn set_watcher_ready(&self) {
+        self.watcher_ready.store(true, Ordering::Relaxed);
+    }
+
+    /// Marks the replica as waiting for leadership. Standby replicas don't watch AtlasUsers and are ready
+    /// without it, so that rolling updates don't wait for a replica that can't become leader yet.
+    pub fn set_standby(&self, standby: bool) {
+        self.standby.store(standby, Ordering::Relaxed);
+    }
+
+    /// Marks the Atlas credentials as successfully verified
+    pub fn set_credentials_verified(&self) {
+        self.credentials_verified.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns why the operator is not ready yet, or `None` if it is ready
+    pub fn not_ready_reason(&self) -> Option<&'static str> {
+        if !self.standby.load(Ordering::Relaxed) && !self.watcher_ready.load(Ordering::Relaxed) {
+            Some("initial list of AtlasUser resources has not completed")
+        } else if !self.credentials_verified.load(Ordering::Relaxed) {
+            Some("Atlas credentials have not been verified")
+        } else {
+            None
+        }
+    }
+
+    /// Records progress of the operator: a finished reconciliation or an event of the AtlasUser watch
+    pub fn record_progress(&self) {
+        *self.last_progress.lock().expect("last progress lock poisoned") = Instant::now();
+    }
+
+    /// Whether the operator is alive: it is waiting for leadership, no reconciliation is in flight, or it made
+    /// progress recently
+    pub fn is_live(&self) -> bool {
+        self.standby.load(Ordering::Relaxed)
+            || self.in_flight.load(Ordering::Relaxed) == 0
+            || self.last_progress().elapsed() < HEARTBEAT_TIMEOUT
+    }
+
+    /// Tracks a reconciliation until the returned guard is dropped
+    pub fn track_reconcile(self: &Arc<Self>) -> ReconcileGuard {
+        // Reconciliations that start after a long idle period must get a full timeout
+        if self.in_flight.fetch_add(1, Ordering::Relaxed) == 0 {
+            self.record_progress();
+        }
+        ReconcileGuard(Arc::clone(self))
+    }
+
+    fn last_progress(&self) -> Instant {
+        *self.last_progress.lock().expect("last progress lock poisoned")
+    }
+}
+
+/// Marks a tracked reconciliation as finished when dropped
+pub struct ReconcileGuard(Arc<Health>);
+
+impl Drop for ReconcileGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.0.record_progress();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_operator_is_live() {
+        let health = Health::default();
+
+        tokio::time::advance(HEARTBEAT_TIMEOUT * 2).await;
+
+        assert!(health.is_live());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn in_flight_reconcile_without_progress_is_not_live() {
+        let health = Arc::new(Health::default());
+        let _reconcile = health.track_reconcile();
+
+        tokio::time::advance(HEARTBEAT_TIMEOUT - Duration::from_secs(1)).await;
+        assert!(health.is_live());
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(!health.is_live());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn progress_keeps_in_flight_reconcile_live() {
+        let health = Arc::new(Health::default());
+        let _stuck = health.track_reconcile();
+
+        tokio::time::advance(HEARTBEAT_TIMEOUT - Duration::from_secs(1)).await;
+        drop(health.track_reconcile());
+        tokio::time::advance(HEARTBEAT_TIMEOUT - Duration::from_secs(1)).await;
+        assert!(health.is_live());
+        health.record_progress();
+        tokio::time::advance(HEARTBEAT_TIMEOUT - Duration::from_secs(1)).await;
+
+        assert!(health.is_live());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconcile_after_idle_period_gets_full_timeout() {
+        let health = Arc::new(Health::default());
+        tokio::time::advance(HEARTBEAT_TIMEOUT * 2).await;
+
+        let _reconcile = health.track_reconcile();
+
+        assert!(health.is_live());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn standby_replica_is_live() {
+        let health = Arc::new(Health::default());
+        let _reconcile = health.track_reconcile();
+        health.set_standby(true);
+
+        tokio::time::advance(HEARTBEAT_TIMEOUT).await;
+
+        assert!(health.is_live());
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use futures::StreamExt;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use kube::runtime::watcher;
+use kube::runtime::WatchStreamExt;
+use kube::Api;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+
+use crate::crd::AtlasUser;
+use crate::health::Health;
+
+/// Index of the watched AtlasUsers by the Atlas user they manage, to detect resources that would fight over the
+/// same user. Of several AtlasUsers managing the same user, the oldest one owns it.
+pub struct AtlasUserIndex {
+    state: RwLock<IndexState>,
+    synced: watch::Sender<bool>,
+}
+
+#[derive(Default)]
+struct IndexState {
+    owners: HashMap<UserKey, BTreeSet<Owner>>,
+    keys: HashMap<(String, String), UserKey>,
+    /// AtlasUsers listed during a (re)initialization of the watch, replacing the index once it is done
+    init: Option<Vec<AtlasUser>>,
+}
+
+/// The Atlas user an AtlasUser manages: its organization and lowercase username
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct UserKey {
+    org_id: String,
+    username: String,
+}
+
+impl UserKey {
+    fn of(atlas_user: &AtlasUser) -> Self {
+        Self {
+            org_id: atlas_user.spec.org_id.clone(),
+            username: atlas_user.spec.username.to_lowercase(),
+        }
+    }
+}
+
+/// An AtlasUser managing an Atlas user, ordered by creation
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Owner {
+    pub created: Option<Time>,
+    pub namespace: String,
+    pub name: String,
+}
+
+impl Owner {
+    fn of(atlas_user: &AtlasUser) -> Self {
+        Self {
+            created: atlas_user.metadata.creation_timestamp.clone(),
+            namespace: atlas_user.metadata.namespace.clone().unwrap_or_default(),
+            name: atlas_user.metadata.name.clone().unwrap_or_default(),
+        }
+    }
+
+    fn is(&self, other: &Owner) -> bool {
+        self.namespace == other.namespace && self.name == other.name
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.namespace, self.name)
+    }
+}
+
+impl Default for AtlasUserIndex {
+    fn default() -> Self {
+        Self {
+            state: RwLock::new(IndexState::default()),
+            synced: watch::Sender::new(false),
+        }
+    }
+}
+
+impl AtlasUserIndex {
+    /// Keeps the index up to date with the AtlasUsers of the given API until the watch ends, recording every watch
+    /// event as progress for the liveness probe
+    pub async fn run(&self, api: Api<AtlasUser>, health: Arc<Health>) {
+        let mut events = watcher(api, watcher::Config::default()).default_backoff().boxed();
+
+        while let Some(event) = events.next().await {
+            if event.is_ok() {
+                health.record_progress();
+            }
+            match event {
+                Ok(watcher::Event::Apply(atlas_user)) => self.apply(&atlas_user),
+                Ok(watcher::Event::Delete(atlas_user)) => self.delete(&atlas_user),
+                Ok(watcher::Event::Init) => self.write().init = Some(Vec::new()),
+                Ok(watcher::Event::InitApply(atlas_user)) => {
+                    self.write().init.get_or_insert_with(Vec::new).push(atlas_user);
+                }
+                Ok(watcher::Event::InitDone) => {
+                    let atlas_users = self.write().init.take().unwrap_or_default();
+                    info!(count = atlas_users.len(), "Indexed AtlasUsers");
+                    self.replace(atlas_users);
+                    self.synced.send_replace(true);
+                }
+                Err(e) => warn!(error = %e, "Watching AtlasUsers for the index failed, retrying"),
+            }
+        }
+    }
+
+    /// Waits until the index holds all AtlasUsers that existed when it started
+    pub async fn synced(&self) {
+        let mut synced = self.synced.subscribe();
+        // The sender lives as long as the index, so waiting cannot fail
+        let _ = synced.wait_for(|synced| *synced).await;
+    }
+
+    /// Adds the AtlasUser to the index or updates it
+    pub fn apply(&self, atlas_user: &AtlasUser) {
+        let mut state = self.write();
+        state.remove(atlas_user);
+        state.insert(atlas_user);
+    }
+
+    /// Removes the AtlasUser from the index
+    pub fn delete(&self, atlas_user: &AtlasUser) {
+        self.write().remove(atlas_user);
+    }
+
+    fn replace(&self, atlas_users: Vec<AtlasUser>) {
+        let mut state = self.write();
+        state.owners.clear();
+        state.keys.clear();
+        for atlas_user in &atlas_users {
+            state.insert(atlas_user);
+        }
+    }
+
+    /// The older AtlasUser that owns the Atlas user this one manages, if any
+    pub fn conflicting_owner(&self, atlas_user: &AtlasUser) -> Option<Owner> {
+        self.other_owner(atlas_user)
+            .filter(|owner| *owner < Owner::of(atlas_user))
+    }
+
+    /// The oldest other AtlasUser managing the same Atlas user as this one, if any
+    pub fn other_owner(&self, atlas_user: &AtlasUser) -> Option<Owner> {
+        let this = Owner::of(atlas_user);
+        let state = self.state.read().expect("index lock poisoned");
+        state
+            .owners
+            .get(&UserKey::of(atlas_user))?
+            .iter()
+            .find(|owner| !owner.is(&this))
+            .cloned()
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, IndexState> {
+        self.state.write().expect("index lock poisoned")
+    }
+}
+
+impl IndexState {
+    fn insert(&mut self, atlas_user: &AtlasUser) {
+        let (owner, key) = (Owner::of(atlas_user), UserKey::of(atlas_user));
+        self.keys
+            .insert((owner.namespace.clone(), owner.name.clone()), key.clone());
+        self.owners.entry(key).or_default().insert(owner);
+    }
+
+    fn remove(&mut self, atlas_user: &AtlasUser) {
+        let this = Owner::of(atlas_user);
+        let Some(key) = self.keys.remove(&(this.namespace.clone(), this.name.clone())) else {
+            return;
+        };
+
+        if let Some(owners) = self.owners.get_mut(&key) {
+            owners.retain(|owner| !owner.is(&this));
+            if owners.is_empty() {
+                self.owners.remove(&key);
+            }
+        }
+    }
+}
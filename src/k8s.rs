@@ -1,7 +1,53 @@
+use std::time::Duration;
+
+use k8s_openapi::api::core::v1::Event;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::api::Patch;
+use kube::api::PatchParams;
+use kube::runtime::wait::await_condition;
+use kube::runtime::wait::conditions;
+use kube::Api;
+use kube::Client;
+use kube::CustomResourceExt;
 use kuberator::cache::StaticApiProvider;
+use kuberator::events::EventRecorder;
 use kuberator::k8s::K8sRepository;
+use tracing::info;
 
 use crate::crd::AtlasUser;
+use crate::error::Error;
+use crate::error::Result;
+
+/// Field manager of the server-side apply of the CustomResourceDefinition
+const FIELD_MANAGER: &str = "mongodb-atlas-k8s-operator";
+/// How long to wait for an installed CustomResourceDefinition to be established
+const CRD_ESTABLISHED_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Type alias for the AtlasUser Kubernetes repository using StaticApiProvider
 pub type AtlasUserK8sRepo = K8sRepository<AtlasUser, StaticApiProvider<AtlasUser>>;
+
+/// Type alias for the Kubernetes Event recorder using StaticApiProvider
+pub type AtlasUserEventRecorder = EventRecorder<StaticApiProvider<Event>>;
+
+/// The AtlasUser CustomResourceDefinition as YAML, as checked in at `crds/atlasusers.yaml`
+pub fn crd_yaml() -> Result<String> {
+    Ok(serde_yaml::to_string(&AtlasUser::crd())?)
+}
+
+/// Server-side applies the AtlasUser CustomResourceDefinition, taking over fields owned by other managers,
+/// and waits until it is established
+pub async fn install_crd(client: Client) -> Result<()> {
+    let crd_api: Api<CustomResourceDefinition> = Api::all(client);
+    let name = AtlasUser::crd_name();
+
+    let params = PatchParams::apply(FIELD_MANAGER).force();
+    crd_api.patch(name, &params, &Patch::Apply(AtlasUser::crd())).await?;
+    info!(name, "Applied CustomResourceDefinition");
+
+    let established = await_condition(crd_api, name, conditions::is_crd_established());
+    tokio::time::timeout(CRD_ESTABLISHED_TIMEOUT, established)
+        .await
+        .map_err(|_| Error::CrdNotEstablished(CRD_ESTABLISHED_TIMEOUT))??;
+
+    Ok(())
+}
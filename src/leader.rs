@@ -0,0 +1,226 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::Utc;
+use k8s_openapi::api::coordination::v1::Lease;
+use k8s_openapi::api::coordination::v1::LeaseSpec;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::MicroTime;
+use kube::api::ObjectMeta;
+use kube::api::PostParams;
+use kube::Api;
+use kube::Client;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::info;
+use tracing::warn;
+
+use crate::config::LeaderElectionConfig;
+
+/// Elects a single active replica of the operator via a `coordination.k8s.io/v1` Lease
+pub struct LeaderElector {
+    api: Api<Lease>,
+    identity: String,
+    config: LeaderElectionConfig,
+}
+
+impl LeaderElector {
+    pub fn new(client: Client, namespace: &str, identity: String, config: LeaderElectionConfig) -> Self {
+        Self {
+            api: Api::namespaced(client, namespace),
+            identity,
+            config,
+        }
+    }
+
+    /// Waits until this replica holds the lease and keeps renewing it in the background
+    pub async fn acquire(self) -> Leadership {
+        info!(lease = %self.config.lease_name, identity = %self.identity, "Waiting to acquire leadership");
+
+        loop {
+            match self.try_acquire_or_renew().await {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => warn!(error = %e, lease = %self.config.lease_name, "Failed to acquire leadership"),
+            }
+            tokio::time::sleep(self.config.retry_period).await;
+        }
+
+        info!(lease = %self.config.lease_name, identity = %self.identity, "Acquired leadership");
+
+        let elector = Arc::new(self);
+        let (lost_tx, lost_rx) = watch::channel(false);
+        let renew_task = tokio::spawn(Arc::clone(&elector).renew(lost_tx));
+
+        Leadership {
+            elector,
+            renew_task,
+            lost: lost_rx,
+        }
+    }
+
+    /// Renews the lease every retry period until it could not be renewed within the renew deadline
+    async fn renew(self: Arc<Self>, lost: watch::Sender<bool>) {
+        let mut last_renewal = Instant::now();
+
+        loop {
+            tokio::time::sleep(self.config.retry_period).await;
+
+            match self.try_acquire_or_renew().await {
+                Ok(true) => last_renewal = Instant::now(),
+                Ok(false) => {
+                    warn!(lease = %self.config.lease_name, "Lease was taken over by another replica");
+                    break;
+                }
+                Err(e) if last_renewal.elapsed() < self.config.renew_deadline => {
+                    warn!(error = %e, lease = %self.config.lease_name, "Failed to renew lease, retrying");
+                }
+                Err(e) => {
+                    warn!(error = %e, lease = %self.config.lease_name, "Failed to renew lease within the renew deadline");
+                    break;
+                }
+            }
+        }
+
+        let _ = lost.send(true);
+    }
+
+    /// Takes the lease if it is free, expired or already held by this replica. Returns whether this replica holds
+    /// the lease afterwards. Concurrent attempts of other replicas are rejected by the API server via the
+    /// resourceVersion of the replaced lease.
+    async fn try_acquire_or_renew(&self) -> kube::Result<bool> {
+        let now = Utc::now();
+        // Validated to fit at startup
+        let lease_duration_seconds = i32::try_from(self.config.lease_duration.as_secs()).unwrap_or(i32::MAX);
+
+        let Some(lease) = self.api.get_opt(&self.config.lease_name).await? else {
+            let lease = Lease {
+                metadata: ObjectMeta {
+                    name: Some(self.config.lease_name.clone()),
+                    ..ObjectMeta::default()
+                },
+                spec: Some(LeaseSpec {
+                    holder_identity: Some(self.identity.clone()),
+                    lease_duration_seconds: Some(lease_duration_seconds),
+                    acquire_time: Some(MicroTime(now)),
+                    renew_time: Some(MicroTime(now)),
+                    lease_transitions: Some(0),
+                    ..LeaseSpec::default()
+                }),
+            };
+            return match self.api.create(&PostParams::default(), &lease).await {
+                Ok(_) => Ok(true),
+                Err(kube::Error::Api(e)) if e.code == 409 => Ok(false),
+                Err(e) => Err(e),
+            };
+        };
+
+        let spec = lease.spec.clone().unwrap_or_default();
+        let held_by_us = spec.holder_identity.as_deref() == Some(self.identity.as_str());
+        let held_by_other = !held_by_us && spec.holder_identity.as_deref().is_some_and(|holder| !holder.is_empty());
+        let expired = spec.renew_time.as_ref().is_none_or(|MicroTime(renew_time)| {
+            let duration = chrono::Duration::seconds(spec.lease_duration_seconds.unwrap_or_default().into());
+            *renew_time + duration < now
+        });
+
+        if held_by_other && !expired {
+            return Ok(false);
+        }
+
+        let spec = if held_by_us {
+            LeaseSpec {
+                lease_duration_seconds: Some(lease_duration_seconds),
+                renew_time: Some(MicroTime(now)),
+                ..spec
+            }
+        } else {
+            LeaseSpec {
+                holder_identity: Some(self.identity.clone()),
+                lease_duration_seconds: Some(lease_duration_seconds),
+                acquire_time: Some(MicroTime(now)),
+                renew_time: Some(MicroTime(now)),
+                lease_transitions: Some(spec.lease_transitions.unwrap_or_default() + 1),
+                ..spec
+            }
+        };
+
+        let lease = Lease {
+            spec: Some(spec),
+            ..lease
+        };
+        match self
+            .api
+            .replace(&self.config.lease_name, &PostParams::default(), &lease)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(kube::Error::Api(e)) if e.code == 409 => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Clears the holder of the lease if this replica still holds it, so that a standby replica can take over
+    /// without waiting for the lease to expire
+    async fn release(&self) -> kube::Result<()> {
+        let Some(lease) = self.api.get_opt(&self.config.lease_name).await? else {
+            return Ok(());
+        };
+        let Some(spec) = lease.spec.clone() else {
+            return Ok(());
+        };
+        if spec.holder_identity.as_deref() != Some(self.identity.as_str()) {
+            return Ok(());
+        }
+
+        let lease = Lease {
+            spec: Some(LeaseSpec {
+                holder_identity: None,
+                lease_duration_seconds: Some(1),
+                renew_time: Some(MicroTime(Utc::now())),
+                ..spec
+            }),
+            ..lease
+        };
+        self.api
+            .replace(&self.config.lease_name, &PostParams::default(), &lease)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Leadership held by this replica, renewed in the background until it is lost or released
+pub struct Leadership {
+    elector: Arc<LeaderElector>,
+    renew_task: JoinHandle<()>,
+    lost: watch::Receiver<bool>,
+}
+
+impl Leadership {
+    /// Resolves once the lease could not be renewed and another replica may have taken over
+    pub fn lost(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut lost = self.lost.clone();
+        async move {
+            let _ = lost.wait_for(|lost| *lost).await;
+        }
+    }
+
+    /// Whether the lease could not be renewed
+    pub fn is_lost(&self) -> bool {
+        *self.lost.borrow()
+    }
+
+    /// Stops renewing the lease and steps down
+    pub async fn release(self) {
+        self.renew_task.abort();
+
+        if self.is_lost() {
+            return;
+        }
+
+        match self.elector.release().await {
+            Ok(()) => info!(lease = %self.elector.config.lease_name, "Released leadership"),
+            Err(e) => warn!(error = %e, lease = %self.elector.config.lease_name, "Failed to release leadership"),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+pub mod atlas;
+pub mod cli;
+pub mod config;
+pub mod crd;
+pub mod error;
+pub mod health;
+pub mod index;
+pub mod k8s;
+pub mod leader;
+pub mod metrics;
+pub mod operator;
+pub mod server;
+pub mod webhook;
@@ -1,63 +1,246 @@
-pub mod atlas;
-pub mod cli;
-pub mod config;
-pub mod crd;
-pub mod error;
-pub mod k8s;
-pub mod operator;
-
 use std::sync::Arc;
+use std::time::Duration;
 
 use clap::Parser;
+use futures::FutureExt;
+use k8s_openapi::api::core::v1::Event;
+use k8s_openapi::api::core::v1::Secret;
 use kube::Api;
 use kube::Client;
 use kuberator::cache::CachingStrategy;
 use kuberator::cache::StaticApiProvider;
+use kuberator::events::EventRecorder;
 use kuberator::k8s::K8sRepository;
 use kuberator::Reconcile;
+use tokio::net::TcpListener;
 use tokio::signal::unix::SignalKind;
 use tracing::info;
+use tracing::warn;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
-use crate::atlas::AtlasUserContext;
-use crate::atlas::AtlasUserRepository;
-use crate::cli::Cli;
-use crate::config::Config;
-use crate::crd::AtlasUser;
-use crate::error::Result;
-use crate::operator::AtlasUserReconciler;
+use mongodb_atlas_k8s_operator::atlas::AtlasClient;
+use mongodb_atlas_k8s_operator::atlas::AtlasConnections;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserApi;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserContext;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserRepository;
+use mongodb_atlas_k8s_operator::cli::Cli;
+use mongodb_atlas_k8s_operator::cli::Command;
+use mongodb_atlas_k8s_operator::cli::CrdCommand;
+use mongodb_atlas_k8s_operator::cli::RunArgs;
+use mongodb_atlas_k8s_operator::cli::WebhookCommand;
+use mongodb_atlas_k8s_operator::config::Config;
+use mongodb_atlas_k8s_operator::config::LeaderElectionConfig;
+use mongodb_atlas_k8s_operator::crd::AtlasUser;
+use mongodb_atlas_k8s_operator::error::Error;
+use mongodb_atlas_k8s_operator::error::Result;
+use mongodb_atlas_k8s_operator::health::Health;
+use mongodb_atlas_k8s_operator::index::AtlasUserIndex;
+use mongodb_atlas_k8s_operator::k8s;
+use mongodb_atlas_k8s_operator::leader::LeaderElector;
+use mongodb_atlas_k8s_operator::metrics::Metrics;
+use mongodb_atlas_k8s_operator::operator::AtlasUserReconciler;
+use mongodb_atlas_k8s_operator::server;
+use mongodb_atlas_k8s_operator::webhook;
+use mongodb_atlas_k8s_operator::webhook::WebhookService;
+
+/// Name of the operator as it appears as source of Kubernetes Events
+const COMPONENT: &str = "mongodb-atlas-k8s-operator";
+/// How long to wait before verifying the Atlas credentials again after a failure
+const CREDENTIALS_RETRY_INTERVAL: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<()> {
     init_tracing();
 
-    let Cli {
-        access_token,
+    match Cli::parse().command {
+        Command::Run(args) => run(*args).await,
+        Command::Crd {
+            command: CrdCommand::Print,
+        } => {
+            print!("{}", k8s::crd_yaml()?);
+            Ok(())
+        }
+        Command::Crd {
+            command: CrdCommand::Install,
+        } => k8s::install_crd(Client::try_default().await?).await,
+        Command::Webhook {
+            command: WebhookCommand::Print(args),
+        } => {
+            let service = WebhookService {
+                name: args.service_name,
+                namespace: args.service_namespace,
+                port: args.service_port,
+                ca_bundle: args.ca_bundle.map(std::fs::read).transpose()?,
+            };
+            print!("{}", serde_yaml::to_string(&webhook::configuration(&service))?);
+            Ok(())
+        }
+    }
+}
+
+/// Runs the operator until it is shut down or loses leadership
+async fn run(args: RunArgs) -> Result<()> {
+    let RunArgs {
+        credentials,
+        atlas_api,
+        webhook,
         config_path,
         namespaces,
-    } = Cli::parse();
+        metrics_address,
+        probe_address,
+        install_crd,
+    } = args;
+
+    let mut config = Config::from_file(&config_path)?;
+    atlas_api.apply(&mut config.atlas_api);
+    config.atlas_api.validate()?;
+    config.leader_election.validate()?;
+
+    let metrics = Arc::new(Metrics::new()?);
+    let metrics_listener = TcpListener::bind(metrics_address).await?;
+    tokio::spawn(server::serve_metrics(metrics_listener, Arc::clone(&metrics)));
 
-    let config = Config::from_file(&config_path)?;
+    let health = Arc::new(Health::default());
+    let probe_listener = TcpListener::bind(probe_address).await?;
+    tokio::spawn(server::serve_probes(probe_listener, Arc::clone(&health)));
 
-    let atlas_repo = Arc::new(AtlasUserRepository::new(access_token)?);
+    let atlas_client = Arc::new(AtlasClient::new(&config.atlas_api, Arc::clone(&metrics))?);
+    let default_atlas_repo = credentials.into_credentials().map(|credentials| {
+        Arc::new(AtlasUserRepository::new(credentials, Arc::clone(&atlas_client))) as Arc<dyn AtlasUserApi>
+    });
     let k8s_client = Client::try_default().await?;
+    if install_crd {
+        k8s::install_crd(k8s_client.clone()).await?;
+    }
+
+    let index = Arc::new(AtlasUserIndex::default());
+    let index_api: Api<AtlasUser> = Api::all(k8s_client.clone());
+    tokio::spawn({
+        let index = Arc::clone(&index);
+        let health = Arc::clone(&health);
+        async move { index.run(index_api, health).await }
+    });
+
+    let webhook_tls = webhook
+        .tls()
+        .map(|tls| tls.load().map(|server_config| (tls, server_config)));
+    let webhook_tls = webhook_tls.transpose()?;
+    let identity_change_policy = config.atlas_user.identity_change_policy;
+
+    let secret_api_provider = StaticApiProvider::<Secret>::new(k8s_client.clone(), &namespaces, CachingStrategy::Adhoc);
+    let atlas_connections = Arc::new(AtlasConnections::new(
+        default_atlas_repo,
+        secret_api_provider,
+        atlas_client,
+    ));
     let api_provider = StaticApiProvider::<AtlasUser>::new(k8s_client.clone(), &namespaces, CachingStrategy::Adhoc);
     let k8s_repo = Arc::new(K8sRepository::new(api_provider));
-    let context = Arc::new(AtlasUserContext::new(atlas_repo, k8s_repo, config.atlas_user));
-    let crd_api: Api<AtlasUser> = Api::all(k8s_client);
-    let reconciler = AtlasUserReconciler::new(crd_api, context);
+    let event_api_provider = StaticApiProvider::<Event>::new(k8s_client.clone(), &namespaces, CachingStrategy::Adhoc);
+    let event_recorder = Arc::new(EventRecorder::new(Arc::new(event_api_provider), COMPONENT));
+    let context = Arc::new(AtlasUserContext::new(
+        Arc::clone(&atlas_connections),
+        k8s_repo,
+        event_recorder,
+        metrics,
+        config.atlas_user,
+        Arc::clone(&index),
+    ));
+    let crd_api: Api<AtlasUser> = Api::all(k8s_client.clone());
+    let reconciler = AtlasUserReconciler::new(crd_api, context, Arc::clone(&health));
+
+    tokio::spawn(verify_atlas_credentials(
+        Arc::clone(&atlas_connections),
+        Arc::clone(&health),
+    ));
+
+    // Conflicting AtlasUsers are only detected once all of them are known, so neither the webhook nor the reconciler
+    // start before
+    index.synced().await;
+
+    if let Some((tls, server_config)) = webhook_tls {
+        tokio::spawn(webhook::serve_webhook(
+            webhook.webhook_address,
+            tls,
+            server_config,
+            Arc::clone(&index),
+            identity_change_policy,
+        ));
+    }
+
+    let shutdown = graceful_shutdown().shared();
+
+    if !config.leader_election.enabled {
+        info!("Starting the MongoDB Atlas Kubernetes Operator");
+        reconciler.start(Some(shutdown)).await;
+        info!("Operator shut down gracefully");
+        return Ok(());
+    }
+
+    let elector = leader_elector(k8s_client, config.leader_election);
+    health.set_standby(true);
+    let leadership = tokio::select! {
+        leadership = elector.acquire() => leadership,
+        _ = shutdown.clone() => {
+            info!("Operator shut down gracefully before acquiring leadership");
+            return Ok(());
+        }
+    };
+    health.set_standby(false);
 
     info!("Starting the MongoDB Atlas Kubernetes Operator");
 
-    reconciler.start(Some(graceful_shutdown())).await;
+    let lost = leadership.lost();
+    let stop = async move {
+        tokio::select! {
+            _ = shutdown => {},
+            _ = lost => warn!("Lost leadership, shutting down"),
+        }
+    };
+    reconciler.start(Some(stop.shared())).await;
+
+    if leadership.is_lost() {
+        return Err(Error::LeadershipLost);
+    }
+    leadership.release().await;
 
     info!("Operator shut down gracefully");
 
     Ok(())
 }
 
+/// Creates the leader elector for this replica, identified by its pod name
+fn leader_elector(k8s_client: Client, config: LeaderElectionConfig) -> LeaderElector {
+    let identity = std::env::var("POD_NAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| format!("{COMPONENT}-{}", std::process::id()));
+    let namespace = config
+        .lease_namespace
+        .clone()
+        .or_else(|| std::env::var("POD_NAMESPACE").ok())
+        .unwrap_or_else(|| k8s_client.default_namespace().to_string());
+
+    LeaderElector::new(k8s_client, &namespace, identity, config)
+}
+
+/// Verifies the default Atlas credentials, retrying until it succeeds, and marks them as verified
+async fn verify_atlas_credentials(atlas_connections: Arc<AtlasConnections>, health: Arc<Health>) {
+    loop {
+        match atlas_connections.verify_default_credentials().await {
+            Ok(()) => {
+                info!("Atlas credentials verified");
+                health.set_credentials_verified();
+                return;
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to verify Atlas credentials, retrying");
+                tokio::time::sleep(CREDENTIALS_RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
 async fn graceful_shutdown() {
     let mut sigterm = tokio::signal::unix::signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
 
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use prometheus::Encoder;
+use prometheus::HistogramOpts;
+use prometheus::HistogramVec;
+use prometheus::IntCounterVec;
+use prometheus::IntGaugeVec;
+use prometheus::Opts;
+use prometheus::Registry;
+use prometheus::TextEncoder;
+use reqwest::Method;
+use reqwest::StatusCode;
+
+use crate::crd::UserOrgMembershipStatus;
+
+const NAMESPACE: &str = "atlas_operator";
+
+/// Prometheus metrics of the operator
+pub struct Metrics {
+    registry: Registry,
+    reconciliations: IntCounterVec,
+    reconcile_duration: HistogramVec,
+    atlas_requests: IntCounterVec,
+    atlas_request_duration: HistogramVec,
+    managed_users: IntGaugeVec,
+    /// Last known membership status per AtlasUser, used to derive the `managed_users` gauge
+    memberships: Mutex<HashMap<String, UserOrgMembershipStatus>>,
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+
+        let reconciliations = IntCounterVec::new(
+            Opts::new("reconciliations_total", "Number of AtlasUser reconciliations").namespace(NAMESPACE),
+            &["action", "outcome"],
+        )?;
+        let reconcile_duration = HistogramVec::new(
+            HistogramOpts::new("reconcile_duration_seconds", "Duration of AtlasUser reconciliations")
+                .namespace(NAMESPACE),
+            &["action", "outcome"],
+        )?;
+        let atlas_requests = IntCounterVec::new(
+            Opts::new("atlas_requests_total", "Number of requests sent to the Atlas Admin API").namespace(NAMESPACE),
+            &["method", "endpoint", "status"],
+        )?;
+        let atlas_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "atlas_request_duration_seconds",
+                "Latency of requests to the Atlas Admin API",
+            )
+            .namespace(NAMESPACE),
+            &["method", "endpoint", "status"],
+        )?;
+        let managed_users = IntGaugeVec::new(
+            Opts::new(
+                "managed_users",
+                "Number of managed users by organization membership status",
+            )
+            .namespace(NAMESPACE),
+            &["membership_status"],
+        )?;
+
+        registry.register(Box::new(reconciliations.clone()))?;
+        registry.register(Box::new(reconcile_duration.clone()))?;
+        registry.register(Box::new(atlas_requests.clone()))?;
+        registry.register(Box::new(atlas_request_duration.clone()))?;
+        registry.register(Box::new(managed_users.clone()))?;
+
+        Ok(Self {
+            registry,
+            reconciliations,
+            reconcile_duration,
+            atlas_requests,
+            atlas_request_duration,
+            managed_users,
+            memberships: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Records a finished reconciliation, e.g. `apply` or `cleanup`, with its outcome
+    pub fn observe_reconcile(&self, action: &str, success: bool, duration: Duration) {
+        let outcome = if success { "success" } else { "error" };
+        self.reconciliations.with_label_values(&[action, outcome]).inc();
+        self.reconcile_duration
+            .with_label_values(&[action, outcome])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Records a request to the Atlas Admin API. `endpoint` is the path template, e.g. `/orgs/{orgId}/users`,
+    /// and `status` is `None` if no response was received.
+    pub fn observe_atlas_request(
+        &self,
+        method: &Method,
+        endpoint: &str,
+        status: Option<StatusCode>,
+        duration: Duration,
+    ) {
+        let status = status.map_or_else(|| "error".to_string(), |status| status.as_u16().to_string());
+        let labels = [method.as_str(), endpoint, status.as_str()];
+        self.atlas_requests.with_label_values(&labels).inc();
+        self.atlas_request_duration
+            .with_label_values(&labels)
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Sets the membership status of a managed user, or forgets the user if `None`
+    pub fn set_membership(&self, key: &str, membership: Option<&UserOrgMembershipStatus>) {
+        let mut memberships = self.memberships.lock().expect("memberships lock poisoned");
+        match membership {
+            Some(membership) => memberships.insert(key.to_string(), membership.clone()),
+            None => memberships.remove(key),
+        };
+
+        for membership in [
+            UserOrgMembershipStatus::Active,
+            UserOrgMembershipStatus::Pending,
+            UserOrgMembershipStatus::Deleted,
+        ] {
+            let count = memberships.values().filter(|m| **m == membership).count();
+            self.managed_users
+                .with_label_values(&[membership_label(&membership)])
+                .set(count as i64);
+        }
+    }
+
+    /// Encodes all metrics in the Prometheus text format
+    pub fn encode(&self) -> prometheus::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+fn membership_label(membership: &UserOrgMembershipStatus) -> &'static str {
+    match membership {
+        UserOrgMembershipStatus::Active => "ACTIVE",
+        UserOrgMembershipStatus::Pending => "PENDING",
+        UserOrgMembershipStatus::Deleted => "DELETED",
+    }
+}
@@ -1,26 +1,38 @@
+use std::future::Future;
 use std::sync::Arc;
 
+use async_trait::async_trait;
+use futures::StreamExt;
 use kube::runtime::watcher::Config;
+use kube::runtime::Controller;
 use kube::Api;
 use kuberator::cache::StaticApiProvider;
 use kuberator::k8s::K8sRepository;
 use kuberator::Reconcile;
+use tracing::info;
 
 use crate::atlas::AtlasUserContext;
 use crate::crd::AtlasUser;
+use crate::health::Health;
 
 /// Reconciler for AtlasUser resources
 pub struct AtlasUserReconciler {
     crd_api: Api<AtlasUser>,
     context: Arc<AtlasUserContext>,
+    health: Arc<Health>,
 }
 
 impl AtlasUserReconciler {
-    pub fn new(crd_api: Api<AtlasUser>, context: Arc<AtlasUserContext>) -> Self {
-        AtlasUserReconciler { crd_api, context }
+    pub fn new(crd_api: Api<AtlasUser>, context: Arc<AtlasUserContext>, health: Arc<Health>) -> Self {
+        AtlasUserReconciler {
+            crd_api,
+            context,
+            health,
+        }
     }
 }
 
+#[async_trait]
 impl
     Reconcile<
         AtlasUser,
@@ -29,6 +41,47 @@ impl
         StaticApiProvider<AtlasUser>,
     > for AtlasUserReconciler
 {
+    /// Runs the controller like the default implementation, additionally marking the health state as
+    /// ready for the watcher once its initial list of AtlasUser resources has completed, and tracking the
+    /// reconciliations in flight for the liveness probe
+    async fn start<G>(self, graceful_trigger: Option<G>)
+    where
+        G: Future<Output = ()> + Send + Sync + 'static,
+    {
+        let health = Arc::clone(&self.health);
+        let (crd_api, config, context) = self.destruct();
+
+        let controller = Controller::new(crd_api, config);
+        let store = controller.store();
+        tokio::spawn({
+            let health = Arc::clone(&health);
+            async move {
+                if store.wait_until_ready().await.is_ok() {
+                    info!("Initial list of AtlasUser resources completed");
+                    health.set_watcher_ready();
+                }
+            }
+        });
+
+        let controller = match graceful_trigger {
+            Some(trigger) => controller.graceful_shutdown_on(trigger),
+            None => controller,
+        };
+
+        let reconcile = move |atlas_user, context| {
+            let reconcile = health.track_reconcile();
+            async move {
+                let action = Self::reconcile(atlas_user, context).await;
+                drop(reconcile);
+                action
+            }
+        };
+        controller
+            .run(reconcile, Self::error_policy, context)
+            .for_each(Self::handle_reconciliation_result)
+            .await;
+    }
+
     fn destruct(self) -> (Api<AtlasUser>, Config, Arc<AtlasUserContext>) {
         (self.crd_api, Config::default(), self.context)
     }
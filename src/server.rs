@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use tokio::net::TcpListener;
+use tracing::error;
+use tracing::info;
+
+use crate::health::Health;
+use crate::metrics::Metrics;
+
+/// Serves the Prometheus metrics on `/metrics`
+pub async fn serve_metrics(listener: TcpListener, metrics: Arc<Metrics>) {
+    let router = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics);
+
+    if let Ok(address) = listener.local_addr() {
+        info!(address = %address, "Serving metrics");
+    }
+
+    if let Err(e) = axum::serve(listener, router).await {
+        error!(error = %e, "Metrics server failed");
+    }
+}
+
+/// Serves the liveness probe on `/healthz` and the readiness probe on `/readyz`
+pub async fn serve_probes(listener: TcpListener, health: Arc<Health>) {
+    let router = Router::new()
+        .route("/healthz", get(liveness_handler))
+        .route("/readyz", get(readiness_handler))
+        .with_state(health);
+
+    if let Ok(address) = listener.local_addr() {
+        info!(address = %address, "Serving health probes");
+    }
+
+    if let Err(e) = axum::serve(listener, router).await {
+        error!(error = %e, "Health probe server failed");
+    }
+}
+
+async fn liveness_handler(State(health): State<Arc<Health>>) -> Response {
+    if health.is_live() {
+        (StatusCode::OK, "ok").into_response()
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "reconcile loop is not making progress").into_response()
+    }
+}
+
+async fn readiness_handler(State(health): State<Arc<Health>>) -> Response {
+    match health.not_ready_reason() {
+        None => (StatusCode::OK, "ok").into_response(),
+        Some(reason) => (StatusCode::SERVICE_UNAVAILABLE, reason).into_response(),
+    }
+}
+
+async fn metrics_handler(State(metrics): State<Arc<Metrics>>) -> Response {
+    match metrics.encode() {
+        Ok(body) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,248 @@
+pub mod validation;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::routing::post;
+use axum::Json;
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use k8s_openapi::api::admissionregistration::v1::RuleWithOperations;
+use k8s_openapi::api::admissionregistration::v1::ServiceReference;
+use k8s_openapi::api::admissionregistration::v1::ValidatingWebhook;
+use k8s_openapi::api::admissionregistration::v1::ValidatingWebhookConfiguration;
+use k8s_openapi::api::admissionregistration::v1::WebhookClientConfig;
+use k8s_openapi::ByteString;
+use kube::api::ObjectMeta;
+use kube::core::admission::AdmissionRequest;
+use kube::core::admission::AdmissionResponse;
+use kube::core::admission::AdmissionReview;
+use kube::core::admission::Operation;
+use kube::core::DynamicObject;
+use kube::CustomResourceExt;
+use kube::ResourceExt;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use rustls::pki_types::PrivateKeyDer;
+use rustls::ServerConfig;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::config::IdentityChangePolicy;
+use crate::crd::AppliedIdentity;
+use crate::crd::AtlasUser;
+use crate::error::Result;
+use crate::index::AtlasUserIndex;
+
+/// Path the validating webhook is served on
+pub const VALIDATE_PATH: &str = "/validate-atlasuser";
+/// Name of the ValidatingWebhookConfiguration
+const CONFIGURATION_NAME: &str = "mongodb-atlas-k8s-operator";
+/// Fully qualified name of the webhook within the configuration
+const WEBHOOK_NAME: &str = "validate.atlasusers.moertel.com";
+/// How long the API server waits for a review before applying the failure policy
+const TIMEOUT_SECONDS: i32 = 5;
+/// How often the certificate and key are read again, to pick up rotated certificates
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Certificate chain and private key the webhook serves HTTPS with, as PEM files
+#[derive(Clone, Debug)]
+pub struct WebhookTls {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl WebhookTls {
+    /// Loads the certificate chain and private key into a TLS server configuration
+    pub fn load(&self) -> Result<Arc<ServerConfig>> {
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)?.collect::<std::result::Result<Vec<_>, _>>()?;
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)?;
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        Ok(Arc::new(config))
+    }
+}
+
+/// The Service the API server reaches the webhook through
+#[derive(Clone, Debug)]
+pub struct WebhookService {
+    pub name: String,
+    pub namespace: String,
+    pub port: i32,
+    /// PEM encoded CA certificates to verify the webhook's certificate with. If not set, it has to be injected,
+    /// e.g. by the cert-manager CA injector.
+    pub ca_bundle: Option<Vec<u8>>,
+}
+
+/// State shared by the requests to the webhook
+#[derive(Clone)]
+struct WebhookState {
+    index: Arc<AtlasUserIndex>,
+    identity_change_policy: IdentityChangePolicy,
+}
+
+/// Serves the validating admission webhook on [`VALIDATE_PATH`] over HTTPS, periodically reloading the certificate
+pub async fn serve_webhook(
+    address: SocketAddr,
+    tls: WebhookTls,
+    server_config: Arc<ServerConfig>,
+    index: Arc<AtlasUserIndex>,
+    identity_change_policy: IdentityChangePolicy,
+) {
+    let rustls_config = RustlsConfig::from_config(server_config);
+    tokio::spawn(reload_tls(tls, rustls_config.clone()));
+
+    info!(address = %address, "Serving validating admission webhook");
+
+    if let Err(e) = axum_server::bind_rustls(address, rustls_config)
+        .serve(router(index, identity_change_policy).into_make_service())
+        .await
+    {
+        error!(error = %e, "Admission webhook server failed");
+    }
+}
+
+/// The routes of the admission webhook, without TLS
+pub fn router(index: Arc<AtlasUserIndex>, identity_change_policy: IdentityChangePolicy) -> Router {
+    Router::new()
+        .route(VALIDATE_PATH, post(validate_handler))
+        .with_state(WebhookState {
+            index,
+            identity_change_policy,
+        })
+}
+
+async fn reload_tls(tls: WebhookTls, rustls_config: RustlsConfig) {
+    let mut interval = tokio::time::interval(TLS_RELOAD_INTERVAL);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        match tls.load() {
+            Ok(server_config) => rustls_config.reload_from_config(server_config),
+            Err(e) => warn!(error = %e, "Failed to reload admission webhook certificate, keeping the current one"),
+        }
+    }
+}
+
+async fn validate_handler(
+    State(state): State<WebhookState>,
+    Json(review): Json<AdmissionReview<AtlasUser>>,
+) -> Json<AdmissionReview<DynamicObject>> {
+    let request: std::result::Result<AdmissionRequest<AtlasUser>, _> = review.try_into();
+    let response = match request {
+        Ok(request) => admit(&request, &state.index, state.identity_change_policy),
+        Err(e) => AdmissionResponse::invalid(e),
+    };
+
+    Json(response.into_review())
+}
+
+/// Reviews the creation or update of an AtlasUser. Updates that leave the spec and annotations unchanged, such as
+/// adding or removing finalizers, are always allowed, so that resources created before the webhook can still be
+/// deleted. New AtlasUsers must not manage the same Atlas user as an existing one. Changes of the username or
+/// organization are denied, unless the operator migrates users to their new identity, in which case the new identity
+/// must not be managed by another AtlasUser either.
+pub fn admit(
+    request: &AdmissionRequest<AtlasUser>,
+    index: &AtlasUserIndex,
+    identity_change_policy: IdentityChangePolicy,
+) -> AdmissionResponse {
+    let mut violations = match (&request.operation, &request.object, &request.old_object) {
+        (Operation::Create, Some(atlas_user), _) => {
+            let mut violations = validation::validate(&atlas_user.spec);
+            violations.extend(duplicate(atlas_user, index));
+            violations
+        }
+        (Operation::Update, Some(atlas_user), Some(old)) if atlas_user.spec != old.spec => match identity_change_policy
+        {
+            IdentityChangePolicy::Reject => validation::validate_update(&old.spec, &atlas_user.spec),
+            IdentityChangePolicy::Migrate => {
+                let mut violations = validation::validate(&atlas_user.spec);
+                if !AppliedIdentity::of(&old.spec).is_same(&AppliedIdentity::of(&atlas_user.spec)) {
+                    violations.extend(duplicate(atlas_user, index));
+                }
+                violations
+            }
+        },
+        _ => Vec::new(),
+    };
+    if let Some(atlas_user) = &request.object {
+        let annotations_changed = request
+            .old_object
+            .as_ref()
+            .is_none_or(|old| old.annotations() != atlas_user.annotations());
+        if annotations_changed {
+            violations.extend(validation::validate_annotations(atlas_user.annotations()));
+        }
+    }
+
+    let response = AdmissionResponse::from(request);
+    if violations.is_empty() {
+        return response;
+    }
+
+    let namespace = request.namespace.as_deref().unwrap_or_default();
+    info!(name = %request.name, namespace = %namespace, ?violations, "Denied AtlasUser");
+    response.deny(violations.join("; "))
+}
+
+/// Reports another AtlasUser already managing the same Atlas user
+fn duplicate(atlas_user: &AtlasUser, index: &AtlasUserIndex) -> Option<String> {
+    let owner = index.other_owner(atlas_user)?;
+    Some(format!(
+        "spec.username: user {:?} in organization {:?} is already managed by AtlasUser {owner}",
+        atlas_user.spec.username, atlas_user.spec.org_id
+    ))
+}
+
+/// The ValidatingWebhookConfiguration registering the webhook for creates and updates of AtlasUsers
+pub fn configuration(service: &WebhookService) -> ValidatingWebhookConfiguration {
+    let crd = AtlasUser::crd();
+    let rule = RuleWithOperations {
+        api_groups: Some(vec![crd.spec.group.clone()]),
+        api_versions: Some(crd.spec.versions.iter().map(|version| version.name.clone()).collect()),
+        operations: Some(vec!["CREATE".to_string(), "UPDATE".to_string()]),
+        resources: Some(vec![crd.spec.names.plural.clone()]),
+        scope: Some("Namespaced".to_string()),
+    };
+
+    let client_config = WebhookClientConfig {
+        ca_bundle: service.ca_bundle.clone().map(ByteString),
+        service: Some(ServiceReference {
+            name: service.name.clone(),
+            namespace: service.namespace.clone(),
+            path: Some(VALIDATE_PATH.to_string()),
+            port: Some(service.port),
+        }),
+        url: None,
+    };
+
+    ValidatingWebhookConfiguration {
+        metadata: ObjectMeta {
+            name: Some(CONFIGURATION_NAME.to_string()),
+            ..ObjectMeta::default()
+        },
+        webhooks: Some(vec![ValidatingWebhook {
+            name: WEBHOOK_NAME.to_string(),
+            admission_review_versions: vec!["v1".to_string()],
+            client_config,
+            failure_policy: Some("Fail".to_string()),
+            match_policy: Some("Equivalent".to_string()),
+            rules: Some(vec![rule]),
+            side_effects: "None".to_string(),
+            timeout_seconds: Some(TIMEOUT_SECONDS),
+            ..ValidatingWebhook::default()
+        }]),
+    }
+}
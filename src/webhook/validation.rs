@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+use crate::crd::AtlasUserSpec;
+use crate::crd::DeletionPolicy;
+use crate::crd::DELETION_POLICY_ANNOTATION;
+
+/// Length of the hexadecimal ObjectIds Atlas uses for organization, group (project) and team IDs
+const OBJECT_ID_LEN: usize = 24;
+
+/// Validates an AtlasUser spec, returning a readable message for every violation
+pub fn validate(spec: &AtlasUserSpec) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    if !is_email(&spec.username) {
+        violations.push(format!(
+            "spec.username: {:?} is not a valid email address",
+            spec.username
+        ));
+    }
+    if !is_object_id(&spec.org_id) {
+        violations.push(format!(
+            "spec.orgId: {:?} is not a 24-character hexadecimal organization ID",
+            spec.org_id
+        ));
+    }
+
+    let mut group_ids = HashSet::new();
+    for (index, assignment) in spec.roles.group_role_assignments.iter().enumerate() {
+        let field = format!("spec.roles.groupRoleAssignments[{index}]");
+        if !is_object_id(&assignment.group_id) {
+            violations.push(format!(
+                "{field}.groupId: {:?} is not a 24-character hexadecimal group (project) ID",
+                assignment.group_id
+            ));
+        }
+        if assignment.group_roles.is_empty() {
+            violations.push(format!("{field}.groupRoles: must contain at least one role"));
+        }
+        if !group_ids.insert(assignment.group_id.to_ascii_lowercase()) {
+            violations.push(format!(
+                "{field}.groupId: group {:?} is assigned more than once, merge its roles into one assignment",
+                assignment.group_id
+            ));
+        }
+    }
+
+    violations
+}
+
+/// Validates an update of an AtlasUser spec: the new spec must be valid, and the user it refers to must stay the
+/// same, as Atlas cannot rename a user or move it to another organization
+pub fn validate_update(old: &AtlasUserSpec, new: &AtlasUserSpec) -> Vec<String> {
+    let mut violations = validate(new);
+
+    if !old.username.eq_ignore_ascii_case(&new.username) {
+        violations.push(format!(
+            "spec.username: is immutable, cannot change {:?} to {:?}",
+            old.username, new.username
+        ));
+    }
+    if old.org_id != new.org_id {
+        violations.push(format!(
+            "spec.orgId: is immutable, cannot change {:?} to {:?}",
+            old.org_id, new.org_id
+        ));
+    }
+
+    violations
+}
+
+/// Validates the annotations of an AtlasUser the operator reads
+pub fn validate_annotations(annotations: &BTreeMap<String, String>) -> Vec<String> {
+    match annotations.get(DELETION_POLICY_ANNOTATION) {
+        Some(value) if value.parse::<DeletionPolicy>().is_err() => vec![format!(
+            "metadata.annotations[{DELETION_POLICY_ANNOTATION}]: {value:?} is not one of Delete, Retain, \
+            RemoveProjectRolesOnly"
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether the value is a plausible email address: a local part and a dotted domain, without whitespace
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && !domain.contains('@')
+        && !value.chars().any(char::is_whitespace)
+        && domain.split('.').count() > 1
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+fn is_object_id(value: &str) -> bool {
+    value.len() == OBJECT_ID_LEN && value.chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -0,0 +1,207 @@
+mod support;
+
+use std::sync::Arc;
+
+use mongodb_atlas_k8s_operator::atlas::auth::Credentials;
+use mongodb_atlas_k8s_operator::atlas::error::Error;
+use mongodb_atlas_k8s_operator::atlas::AtlasClient;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserApi;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserRepository;
+use mongodb_atlas_k8s_operator::config::AtlasApiConfig;
+use mongodb_atlas_k8s_operator::config::RetryConfig;
+use mongodb_atlas_k8s_operator::metrics::Metrics;
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::support::auth::MockAuth;
+use crate::support::auth::CLIENT_ID;
+use crate::support::auth::CLIENT_SECRET;
+use crate::support::auth::PRIVATE_KEY;
+use crate::support::auth::PUBLIC_KEY;
+
+const ORGS: &str = "GET /api/atlas/v2/orgs";
+const TOKEN: &str = "POST /api/oauth/token";
+
+fn repository(base_url: Url, credentials: Credentials) -> AtlasUserRepository {
+    let config = AtlasApiConfig {
+        base_url,
+        retry: RetryConfig {
+            max_attempts: 1,
+            ..RetryConfig::default()
+        },
+        ..AtlasApiConfig::default()
+    };
+    let metrics = Arc::new(Metrics::new().expect("metrics registry"));
+    let atlas_client = Arc::new(AtlasClient::new(&config, metrics).expect("Atlas client"));
+    AtlasUserRepository::new(credentials, atlas_client)
+}
+
+fn api_key(private_key: &str) -> Credentials {
+    Credentials::ApiKey {
+        public_key: Arc::from(PUBLIC_KEY),
+        private_key: Arc::from(private_key),
+    }
+}
+
+fn service_account(client_secret: &str) -> Credentials {
+    Credentials::ServiceAccount {
+        client_id: Arc::from(CLIENT_ID),
+        client_secret: Arc::from(client_secret),
+    }
+}
+
+fn nonce(nonce: &str, nc: u32) -> (String, u32) {
+    (nonce.to_string(), nc)
+}
+
+#[tokio::test]
+async fn digest_request_is_sent_again_after_challenge() {
+    let (auth, base_url) = MockAuth::start().await;
+    let atlas_repo = repository(base_url, api_key(PRIVATE_KEY));
+
+    atlas_repo.verify_credentials().await.unwrap();
+
+    assert_eq!(auth.requests(), [format!("{ORGS} 401"), format!("{ORGS} 200")]);
+    assert_eq!(auth.digests(), [nonce("nonce-1", 1)]);
+}
+
+#[tokio::test]
+async fn digest_nonce_is_reused_with_increasing_count() {
+    let (auth, base_url) = MockAuth::start().await;
+    let atlas_repo = repository(base_url, api_key(PRIVATE_KEY));
+
+    atlas_repo.verify_credentials().await.unwrap();
+    atlas_repo.verify_credentials().await.unwrap();
+    atlas_repo.verify_credentials().await.unwrap();
+
+    assert_eq!(
+        auth.requests(),
+        [
+            format!("{ORGS} 401"),
+            format!("{ORGS} 200"),
+            format!("{ORGS} 200"),
+            format!("{ORGS} 200")
+        ]
+    );
+    assert_eq!(
+        auth.digests(),
+        [nonce("nonce-1", 1), nonce("nonce-1", 2), nonce("nonce-1", 3)]
+    );
+}
+
+#[tokio::test]
+async fn stale_digest_nonce_is_challenged_again() {
+    let (auth, base_url) = MockAuth::start().await;
+    let atlas_repo = repository(base_url, api_key(PRIVATE_KEY));
+    atlas_repo.verify_credentials().await.unwrap();
+
+    auth.rotate_nonce();
+    atlas_repo.verify_credentials().await.unwrap();
+
+    assert_eq!(
+        auth.requests(),
+        [
+            format!("{ORGS} 401"),
+            format!("{ORGS} 200"),
+            format!("{ORGS} 401"),
+            format!("{ORGS} 200")
+        ]
+    );
+    assert_eq!(auth.digests(), [nonce("nonce-1", 1), nonce("nonce-2", 1)]);
+}
+
+#[tokio::test]
+async fn rejected_digest_credentials_fail_after_one_challenge() {
+    let (auth, base_url) = MockAuth::start().await;
+    let atlas_repo = repository(base_url, api_key("wrong-private-key"));
+
+    let result = atlas_repo.verify_credentials().await;
+
+    assert!(
+        matches!(
+            result,
+            Err(Error::Api {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ),
+        "{result:?}"
+    );
+    assert_eq!(auth.requests(), [format!("{ORGS} 401"), format!("{ORGS} 401")]);
+    assert_eq!(auth.digests(), []);
+}
+
+#[tokio::test]
+async fn token_is_reused_until_refresh_margin() {
+    let (auth, base_url) = MockAuth::start().await;
+    let atlas_repo = repository(base_url, service_account(CLIENT_SECRET));
+
+    atlas_repo.verify_credentials().await.unwrap();
+    atlas_repo.verify_credentials().await.unwrap();
+
+    assert_eq!(
+        auth.requests(),
+        [format!("{TOKEN} 200"), format!("{ORGS} 200"), format!("{ORGS} 200")]
+    );
+}
+
+#[tokio::test]
+async fn token_expiring_within_refresh_margin_is_requested_again() {
+    let (auth, base_url) = MockAuth::start().await;
+    auth.set_expires_in(60);
+    let atlas_repo = repository(base_url, service_account(CLIENT_SECRET));
+
+    atlas_repo.verify_credentials().await.unwrap();
+    atlas_repo.verify_credentials().await.unwrap();
+
+    assert_eq!(
+        auth.requests(),
+        [
+            format!("{TOKEN} 200"),
+            format!("{ORGS} 200"),
+            format!("{TOKEN} 200"),
+            format!("{ORGS} 200")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rejected_token_is_refreshed_and_request_sent_again() {
+    let (auth, base_url) = MockAuth::start().await;
+    let atlas_repo = repository(base_url, service_account(CLIENT_SECRET));
+    atlas_repo.verify_credentials().await.unwrap();
+
+    auth.revoke_tokens();
+    atlas_repo.verify_credentials().await.unwrap();
+
+    assert_eq!(
+        auth.requests(),
+        [
+            format!("{TOKEN} 200"),
+            format!("{ORGS} 200"),
+            format!("{ORGS} 401"),
+            format!("{TOKEN} 200"),
+            format!("{ORGS} 200")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn failed_token_request_is_reported() {
+    let (auth, base_url) = MockAuth::start().await;
+    let atlas_repo = repository(base_url, service_account("wrong-client-secret"));
+
+    let result = atlas_repo.verify_credentials().await;
+
+    assert!(
+        matches!(
+            result,
+            Err(Error::TokenRequest {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ),
+        "{result:?}"
+    );
+    assert_eq!(auth.requests(), [format!("{TOKEN} 401")]);
+}
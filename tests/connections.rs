@@ -0,0 +1,93 @@
+mod support;
+
+use std::sync::Arc;
+
+use k8s_openapi::api::core::v1::Secret;
+use mongodb_atlas_k8s_operator::atlas::AtlasClient;
+use mongodb_atlas_k8s_operator::atlas::AtlasConnections;
+use mongodb_atlas_k8s_operator::config::AtlasApiConfig;
+use mongodb_atlas_k8s_operator::crd::AtlasUser;
+use mongodb_atlas_k8s_operator::crd::SecretReference;
+use mongodb_atlas_k8s_operator::metrics::Metrics;
+
+use crate::support::atlas_user;
+use crate::support::connection_secret;
+use crate::support::kube::FakeKube;
+use crate::support::kube::NAMESPACE;
+use crate::support::CREATED;
+
+const SECRET_NAME: &str = "atlas-credentials";
+
+fn connections(kube: &FakeKube) -> AtlasConnections {
+    let metrics = Arc::new(Metrics::new().expect("metrics registry"));
+    let atlas_client = Arc::new(AtlasClient::new(&AtlasApiConfig::default(), metrics).expect("Atlas client"));
+    AtlasConnections::new(None, kube.secret_api_provider(), atlas_client)
+}
+
+fn secret(resource_version: &str) -> Secret {
+    let mut secret = connection_secret(SECRET_NAME, "secret-token");
+    secret.metadata.resource_version = Some(resource_version.to_string());
+    secret
+}
+
+fn secret_user(name: &str) -> AtlasUser {
+    let mut atlas_user = atlas_user(NAMESPACE, name, CREATED);
+    atlas_user.spec.connection_secret_ref = Some(SecretReference {
+        name: SECRET_NAME.to_string(),
+    });
+    atlas_user
+}
+
+#[tokio::test]
+async fn repository_is_reused_until_secret_changes() {
+    let kube = FakeKube::default();
+    kube.insert_secret(&secret("1"));
+    let connections = connections(&kube);
+    let atlas_user = secret_user("john-doe");
+
+    let first = connections.repository(&atlas_user).await.unwrap();
+    let second = connections.repository(&atlas_user).await.unwrap();
+    kube.insert_secret(&secret("2"));
+    let rotated = connections.repository(&atlas_user).await.unwrap();
+
+    assert!(Arc::ptr_eq(&first, &second));
+    assert!(!Arc::ptr_eq(&first, &rotated));
+}
+
+#[tokio::test]
+async fn repository_is_evicted_once_released_by_all_users() {
+    let kube = FakeKube::default();
+    kube.insert_secret(&secret("1"));
+    let connections = connections(&kube);
+    let john = secret_user("john-doe");
+    let jane = secret_user("jane-doe");
+
+    let cached = connections.repository(&john).await.unwrap();
+    connections.repository(&jane).await.unwrap();
+    connections.release(&john);
+    let still_used = connections.repository(&jane).await.unwrap();
+    connections.release(&jane);
+    let rebuilt = connections.repository(&jane).await.unwrap();
+
+    assert!(Arc::ptr_eq(&cached, &still_used));
+    assert!(!Arc::ptr_eq(&cached, &rebuilt));
+}
+
+#[tokio::test]
+async fn repository_is_evicted_once_users_stop_referencing_secret() {
+    let kube = FakeKube::default();
+    kube.insert_secret(&secret("1"));
+    let connections = connections(&kube);
+    let mut atlas_user = secret_user("john-doe");
+
+    let cached = connections.repository(&atlas_user).await.unwrap();
+    let secret_ref = atlas_user.spec.connection_secret_ref.take();
+    assert!(
+        connections.repository(&atlas_user).await.is_err(),
+        "no default credentials"
+    );
+    atlas_user.spec.connection_secret_ref = secret_ref;
+    let rebuilt = connections.repository(&atlas_user).await.unwrap();
+
+    assert!(!Arc::ptr_eq(&cached, &rebuilt));
+}
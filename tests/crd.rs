@@ -0,0 +1,13 @@
+use mongodb_atlas_k8s_operator::k8s::crd_yaml;
+
+const CHECKED_IN_CRD: &str = include_str!("../crds/atlasusers.yaml");
+
+#[test]
+fn checked_in_crd_matches_generated() {
+    let generated = crd_yaml().expect("serializable CustomResourceDefinition");
+
+    assert_eq!(
+        CHECKED_IN_CRD, generated,
+        "crds/atlasusers.yaml is outdated, regenerate it with `cargo run -- crd print > crds/atlasusers.yaml`"
+    );
+}
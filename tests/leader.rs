@@ -0,0 +1,130 @@
+mod support;
+
+use std::time::Duration;
+
+use chrono::Utc;
+use k8s_openapi::api::coordination::v1::Lease;
+use k8s_openapi::api::coordination::v1::LeaseSpec;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::MicroTime;
+use kube::api::ObjectMeta;
+use mongodb_atlas_k8s_operator::config::LeaderElectionConfig;
+use mongodb_atlas_k8s_operator::leader::LeaderElector;
+
+use crate::support::kube::FakeKube;
+use crate::support::kube::NAMESPACE;
+
+const LEASE_NAME: &str = "mongodb-atlas-k8s-operator";
+const IDENTITY: &str = "operator-0";
+const OTHER_IDENTITY: &str = "operator-1";
+
+fn elector(kube: &FakeKube, identity: &str) -> LeaderElector {
+    let config = LeaderElectionConfig {
+        enabled: true,
+        ..LeaderElectionConfig::default()
+    };
+    LeaderElector::new(kube.client(), NAMESPACE, identity.to_string(), config)
+}
+
+/// A Lease held by another replica that last renewed it `renewed_ago`
+fn lease_held_by_other(renewed_ago: chrono::Duration) -> Lease {
+    let renew_time = Utc::now() - renewed_ago;
+    Lease {
+        metadata: ObjectMeta {
+            name: Some(LEASE_NAME.to_string()),
+            ..ObjectMeta::default()
+        },
+        spec: Some(LeaseSpec {
+            holder_identity: Some(OTHER_IDENTITY.to_string()),
+            lease_duration_seconds: Some(15),
+            acquire_time: Some(MicroTime(renew_time)),
+            renew_time: Some(MicroTime(renew_time)),
+            lease_transitions: Some(3),
+            ..LeaseSpec::default()
+        }),
+    }
+}
+
+fn lease_spec(kube: &FakeKube) -> LeaseSpec {
+    kube.lease(LEASE_NAME).expect("Lease").spec.expect("Lease spec")
+}
+
+#[tokio::test(start_paused = true)]
+async fn free_lease_is_acquired() {
+    let kube = FakeKube::default();
+
+    let leadership = elector(&kube, IDENTITY).acquire().await;
+
+    let spec = lease_spec(&kube);
+    assert_eq!(spec.holder_identity.as_deref(), Some(IDENTITY));
+    assert_eq!(spec.lease_duration_seconds, Some(15));
+    assert_eq!(spec.lease_transitions, Some(0));
+    assert!(!leadership.is_lost());
+}
+
+#[tokio::test(start_paused = true)]
+async fn expired_lease_is_taken_over() {
+    let kube = FakeKube::default();
+    kube.insert_lease(&lease_held_by_other(chrono::Duration::seconds(60)));
+
+    elector(&kube, IDENTITY).acquire().await;
+
+    let spec = lease_spec(&kube);
+    assert_eq!(spec.holder_identity.as_deref(), Some(IDENTITY));
+    assert_eq!(spec.lease_transitions, Some(4));
+}
+
+#[tokio::test(start_paused = true)]
+async fn lease_held_by_other_replica_is_not_taken() {
+    let kube = FakeKube::default();
+    kube.insert_lease(&lease_held_by_other(chrono::Duration::zero()));
+
+    let acquired = tokio::time::timeout(Duration::from_secs(10), elector(&kube, IDENTITY).acquire()).await;
+
+    assert!(acquired.is_err(), "acquired a Lease held by another replica");
+    let spec = lease_spec(&kube);
+    assert_eq!(spec.holder_identity.as_deref(), Some(OTHER_IDENTITY));
+    assert_eq!(spec.lease_transitions, Some(3));
+}
+
+#[tokio::test(start_paused = true)]
+async fn lease_is_renewed_while_held() {
+    let kube = FakeKube::default();
+    let leadership = elector(&kube, IDENTITY).acquire().await;
+    let acquired = kube.lease(LEASE_NAME).expect("Lease").metadata.resource_version;
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    let renewed = kube.lease(LEASE_NAME).expect("Lease").metadata.resource_version;
+    assert_ne!(acquired, renewed);
+    assert_eq!(lease_spec(&kube).holder_identity.as_deref(), Some(IDENTITY));
+    assert!(!leadership.is_lost());
+}
+
+#[tokio::test(start_paused = true)]
+async fn leadership_is_lost_once_lease_is_taken_over() {
+    let kube = FakeKube::default();
+    let leadership = elector(&kube, IDENTITY).acquire().await;
+
+    kube.insert_lease(&lease_held_by_other(chrono::Duration::zero()));
+
+    tokio::time::timeout(Duration::from_secs(10), leadership.lost())
+        .await
+        .expect("leadership lost");
+    assert!(leadership.is_lost());
+}
+
+#[tokio::test(start_paused = true)]
+async fn released_lease_is_acquired_by_other_replica_without_waiting() {
+    let kube = FakeKube::default();
+    let leadership = elector(&kube, IDENTITY).acquire().await;
+
+    leadership.release().await;
+    assert_eq!(lease_spec(&kube).holder_identity, None);
+    let start = tokio::time::Instant::now();
+    elector(&kube, OTHER_IDENTITY).acquire().await;
+
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    let spec = lease_spec(&kube);
+    assert_eq!(spec.holder_identity.as_deref(), Some(OTHER_IDENTITY));
+    assert_eq!(spec.lease_transitions, Some(1));
+}
@@ -0,0 +1,1155 @@
+mod support;
+
+use std::sync::Arc;
+
+use kube::runtime::controller::Action;
+use kube::ResourceExt;
+use kuberator::Context;
+use mongodb_atlas_k8s_operator::atlas::error::Error;
+use mongodb_atlas_k8s_operator::config::AtlasUserConfig;
+use mongodb_atlas_k8s_operator::config::IdentityChangePolicy;
+use mongodb_atlas_k8s_operator::crd::AdoptionPolicy;
+use mongodb_atlas_k8s_operator::crd::AppliedIdentity;
+use mongodb_atlas_k8s_operator::crd::AtlasUser;
+use mongodb_atlas_k8s_operator::crd::AtlasUserSpec;
+use mongodb_atlas_k8s_operator::crd::AtlasUserStatus;
+use mongodb_atlas_k8s_operator::crd::ConditionStatus;
+use mongodb_atlas_k8s_operator::crd::ConditionType;
+use mongodb_atlas_k8s_operator::crd::DeletionPolicy;
+use mongodb_atlas_k8s_operator::crd::ExternalDeletionPolicy;
+use mongodb_atlas_k8s_operator::crd::GroupRoleAssignment;
+use mongodb_atlas_k8s_operator::crd::GroupRoleName;
+use mongodb_atlas_k8s_operator::crd::OrgRoleName;
+use mongodb_atlas_k8s_operator::crd::PreviousMembership;
+use mongodb_atlas_k8s_operator::crd::UserOrgMembershipStatus;
+use mongodb_atlas_k8s_operator::crd::UserOrigin;
+use mongodb_atlas_k8s_operator::crd::DELETION_POLICY_ANNOTATION;
+use reqwest::StatusCode;
+
+use crate::support::atlas_user;
+use crate::support::config;
+use crate::support::fake::AtlasCall;
+use crate::support::fake::FakeAtlasUserApi;
+use crate::support::reconciled_status;
+use crate::support::spec;
+use crate::support::Harness;
+use crate::support::GROUP_ID;
+use crate::support::ORG_ID;
+use crate::support::REQUEUE;
+use crate::support::USERNAME;
+
+fn invite() -> AtlasCall {
+    AtlasCall::Invite {
+        org_id: ORG_ID.to_string(),
+        username: USERNAME.to_string(),
+    }
+}
+
+fn find_by_username() -> AtlasCall {
+    AtlasCall::FindByUsername {
+        org_id: ORG_ID.to_string(),
+        username: USERNAME.to_string(),
+    }
+}
+
+fn get(user_id: &str) -> AtlasCall {
+    AtlasCall::Get {
+        org_id: ORG_ID.to_string(),
+        user_id: user_id.to_string(),
+    }
+}
+
+fn update(user_id: &str) -> AtlasCall {
+    AtlasCall::Update {
+        org_id: ORG_ID.to_string(),
+        user_id: user_id.to_string(),
+    }
+}
+
+fn delete(user_id: &str) -> AtlasCall {
+    AtlasCall::Delete {
+        org_id: ORG_ID.to_string(),
+        user_id: user_id.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn new_resource_is_looked_up_then_invited() {
+    let harness = Harness::with_fake(config());
+    let atlas_user = harness.atlas_user(1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username(), invite()]);
+    assert_eq!(harness.status().origin, Some(UserOrigin::Created));
+}
+
+#[tokio::test]
+async fn unchanged_resource_only_gets_user() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get(&user_id)]);
+}
+
+#[tokio::test]
+async fn changed_spec_only_updates_user() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, Default::default());
+    let atlas_user = harness.atlas_user(2, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [update(&user_id)]);
+    let user = harness.atlas.user(ORG_ID, &user_id).expect("user kept");
+    assert_eq!(user.roles, spec().roles);
+}
+
+#[tokio::test]
+async fn drifted_user_is_read_then_corrected() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, Default::default());
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get(&user_id), update(&user_id)]);
+}
+
+#[tokio::test]
+async fn lost_user_id_is_looked_up_instead_of_invited() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user(2, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username()]);
+    assert_eq!(harness.status().user_id.as_deref(), Some(user_id.as_str()));
+}
+
+#[tokio::test]
+async fn lost_user_id_invites_when_lookup_finds_nothing() {
+    let harness = Harness::with_fake(config());
+    let atlas_user = harness.atlas_user(2, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username(), invite()]);
+}
+
+#[tokio::test]
+async fn existing_org_member_is_adopted_instead_of_invited() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, Default::default());
+    let atlas_user = harness.atlas_user(1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username()]);
+    let status = harness.status();
+    assert_eq!(status.user_id.as_deref(), Some(user_id.as_str()));
+    assert_eq!(status.origin, Some(UserOrigin::Adopted));
+    assert_eq!(harness.kube.event_reasons(), ["Adopted"]);
+}
+
+#[tokio::test]
+async fn adopt_if_roles_match_adopts_member_with_matching_roles() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let spec = AtlasUserSpec {
+        adoption_policy: Some(AdoptionPolicy::AdoptIfRolesMatch),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username()]);
+    let status = harness.status();
+    assert_eq!(status.user_id.as_deref(), Some(user_id.as_str()));
+    assert_eq!(status.origin, Some(UserOrigin::Adopted));
+}
+
+#[tokio::test]
+async fn adopt_if_roles_match_refuses_member_with_other_roles() {
+    let harness = Harness::with_fake(config());
+    harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, Default::default());
+    let spec = AtlasUserSpec {
+        adoption_policy: Some(AdoptionPolicy::AdoptIfRolesMatch),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username()], "no invite");
+    let status = harness.status();
+    assert_eq!(status.user_id, None);
+    assert!(status.error.unwrap().contains("is not adopted"));
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "PermanentFailure".to_string())
+    );
+}
+
+#[tokio::test]
+async fn never_adoption_policy_refuses_existing_member() {
+    let config = AtlasUserConfig {
+        adoption_policy: AdoptionPolicy::Never,
+        ..config()
+    };
+    let harness = Harness::with_fake(config);
+    harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user(1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username()], "no invite");
+    let status = harness.status();
+    assert_eq!(status.user_id, None);
+    assert!(status.error.unwrap().contains("is not adopted"));
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "PermanentFailure".to_string())
+    );
+}
+
+#[tokio::test]
+async fn cleanup_after_refused_adoption_makes_no_calls() {
+    let config = AtlasUserConfig {
+        adoption_policy: AdoptionPolicy::Never,
+        ..config()
+    };
+    let harness = Harness::with_fake(config);
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    harness.context.handle_apply(harness.atlas_user(1, None)).await.unwrap();
+
+    let atlas_user = harness.atlas_user(1, Some(harness.status()));
+    let action = harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), [find_by_username()]);
+    assert!(harness.atlas.user(ORG_ID, &user_id).is_some());
+}
+
+#[tokio::test]
+async fn cleanup_keeps_adopted_user_by_default() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let status = AtlasUserStatus {
+        origin: Some(UserOrigin::Adopted),
+        ..applied_status(&user_id)
+    };
+    let atlas_user = harness.atlas_user(1, Some(status));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), []);
+    assert!(harness.atlas.user(ORG_ID, &user_id).is_some());
+}
+
+#[tokio::test]
+async fn cleanup_deletes_adopted_user_with_explicit_delete_policy() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let status = AtlasUserStatus {
+        origin: Some(UserOrigin::Adopted),
+        ..applied_status(&user_id)
+    };
+    let spec = AtlasUserSpec {
+        deletion_policy: Some(DeletionPolicy::Delete),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 1, Some(status));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [delete(&user_id)]);
+}
+
+#[tokio::test]
+async fn failed_call_is_not_followed_by_further_calls() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    harness.atlas.fail_next(Error::MissingCredentials);
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(REQUEUE));
+    assert_eq!(harness.atlas.calls(), [get(&user_id)]);
+}
+
+#[tokio::test]
+async fn cleanup_only_deletes_user() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [delete(&user_id)]);
+    assert!(harness.atlas.user(ORG_ID, &user_id).is_none());
+}
+
+#[tokio::test]
+async fn cleanup_makes_no_calls_unless_safe_to_delete() {
+    let config = AtlasUserConfig {
+        safe_to_delete: false,
+        ..config()
+    };
+    let harness = Harness::with_fake(config);
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), []);
+}
+
+fn conflict(harness: &Harness<Arc<FakeAtlasUserApi>>) -> Option<(ConditionStatus, String)> {
+    let status = harness.status();
+    let condition = status
+        .conditions
+        .iter()
+        .find(|condition| condition.type_ == ConditionType::Conflict)?;
+    Some((condition.status, condition.message.clone()))
+}
+
+#[tokio::test]
+async fn newer_duplicate_makes_no_calls() {
+    let harness = Harness::with_fake(config());
+    harness
+        .index
+        .apply(&atlas_user("team-a", "john", "2025-12-01T00:00:00Z"));
+    let atlas_user = harness.atlas_user(1, None);
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(REQUEUE));
+    assert_eq!(harness.atlas.calls(), []);
+    assert_eq!(
+        conflict(&harness),
+        Some((
+            ConditionStatus::True,
+            format!("User {USERNAME} in organization {ORG_ID} is already managed by AtlasUser team-a/john")
+        ))
+    );
+    assert_eq!(harness.status().user_id, None);
+    assert_eq!(harness.kube.event_reasons(), ["Conflict"]);
+
+    let atlas_user = harness.atlas_user(1, Some(harness.status()));
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), []);
+    assert_eq!(harness.kube.event_reasons(), ["Conflict"], "conflict reported once");
+}
+
+#[tokio::test]
+async fn older_duplicate_is_reconciled() {
+    let harness = Harness::with_fake(config());
+    harness
+        .index
+        .apply(&atlas_user("team-a", "john", "2026-02-01T00:00:00Z"));
+    let atlas_user = harness.atlas_user(1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username(), invite()]);
+    assert_eq!(conflict(&harness), None);
+}
+
+#[tokio::test]
+async fn duplicate_with_other_username_case_conflicts() {
+    let harness = Harness::with_fake(config());
+    let mut older = atlas_user("team-a", "john", "2025-12-01T00:00:00Z");
+    older.spec.username = USERNAME.to_uppercase();
+    harness.index.apply(&older);
+    let atlas_user = harness.atlas_user(1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), []);
+}
+
+#[tokio::test]
+async fn conflict_is_resolved_once_older_duplicate_is_deleted() {
+    let harness = Harness::with_fake(config());
+    let older = atlas_user("team-a", "john", "2025-12-01T00:00:00Z");
+    harness.index.apply(&older);
+    harness.context.handle_apply(harness.atlas_user(1, None)).await.unwrap();
+
+    harness.index.delete(&older);
+    let atlas_user = harness.atlas_user(1, Some(harness.status()));
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [find_by_username(), invite()]);
+    assert_eq!(
+        conflict(&harness),
+        Some((
+            ConditionStatus::False,
+            "No older AtlasUser manages the same user".to_string()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn cleanup_of_newer_duplicate_makes_no_calls() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    harness
+        .index
+        .apply(&atlas_user("team-a", "john", "2025-12-01T00:00:00Z"));
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    let action = harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), []);
+}
+
+const NEW_USERNAME: &str = "jane.doe@example.com";
+const NEW_ORG_ID: &str = "0f1e2d3c4b5a69788796a5b4";
+
+fn migrate_config() -> AtlasUserConfig {
+    AtlasUserConfig {
+        identity_change_policy: IdentityChangePolicy::Migrate,
+        ..config()
+    }
+}
+
+fn applied_status(user_id: &str) -> AtlasUserStatus {
+    AtlasUserStatus {
+        applied_identity: Some(AppliedIdentity::of(&spec())),
+        ..reconciled_status(user_id, 1)
+    }
+}
+
+fn renamed_spec() -> AtlasUserSpec {
+    AtlasUserSpec {
+        username: NEW_USERNAME.to_string(),
+        ..spec()
+    }
+}
+
+#[tokio::test]
+async fn changed_identity_is_rejected_without_calls() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user_with_spec(renamed_spec(), 2, Some(applied_status(&user_id)));
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), []);
+    let status = harness.status();
+    assert_eq!(status.user_id.as_deref(), Some(user_id.as_str()));
+    assert_eq!(status.applied_identity, Some(AppliedIdentity::of(&spec())));
+    assert!(status.error.unwrap().contains("is not allowed"));
+}
+
+#[tokio::test]
+async fn changed_username_case_is_not_an_identity_change() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, Default::default());
+    let spec = AtlasUserSpec {
+        username: USERNAME.to_uppercase(),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 2, Some(applied_status(&user_id)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.calls(),
+        [AtlasCall::Update {
+            org_id: ORG_ID.to_string(),
+            user_id: user_id.clone(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn status_without_applied_identity_records_it() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get(&user_id)]);
+    assert_eq!(harness.status().applied_identity, Some(AppliedIdentity::of(&spec())));
+}
+
+#[tokio::test]
+async fn changed_username_is_migrated() {
+    let harness = Harness::with_fake(migrate_config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user_with_spec(renamed_spec(), 2, Some(applied_status(&user_id)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.calls(),
+        [
+            AtlasCall::FindByUsername {
+                org_id: ORG_ID.to_string(),
+                username: NEW_USERNAME.to_string(),
+            },
+            AtlasCall::Invite {
+                org_id: ORG_ID.to_string(),
+                username: NEW_USERNAME.to_string(),
+            },
+            delete(&user_id),
+        ]
+    );
+    let status = harness.status();
+    assert_ne!(status.user_id.as_deref(), Some(user_id.as_str()));
+    assert_eq!(status.applied_identity, Some(AppliedIdentity::of(&renamed_spec())));
+    assert_eq!(status.observed_generation, Some(2));
+    assert_eq!(status.previous_membership, None);
+    assert_eq!(harness.kube.event_reasons(), ["Migrated", "Removed"]);
+}
+
+#[tokio::test]
+async fn changed_org_is_migrated_and_previous_membership_removed_from_previous_org() {
+    let harness = Harness::with_fake(migrate_config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let spec = AtlasUserSpec {
+        org_id: NEW_ORG_ID.to_string(),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 2, Some(applied_status(&user_id)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.calls(),
+        [
+            AtlasCall::FindByUsername {
+                org_id: NEW_ORG_ID.to_string(),
+                username: USERNAME.to_string(),
+            },
+            AtlasCall::Invite {
+                org_id: NEW_ORG_ID.to_string(),
+                username: USERNAME.to_string(),
+            },
+            delete(&user_id),
+        ]
+    );
+    assert!(harness.atlas.user(ORG_ID, &user_id).is_none());
+}
+
+#[tokio::test]
+async fn migration_keeps_previous_membership_unless_safe_to_delete() {
+    let config = AtlasUserConfig {
+        safe_to_delete: false,
+        ..migrate_config()
+    };
+    let harness = Harness::with_fake(config);
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user_with_spec(renamed_spec(), 2, Some(applied_status(&user_id)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.calls(),
+        [
+            AtlasCall::FindByUsername {
+                org_id: ORG_ID.to_string(),
+                username: NEW_USERNAME.to_string(),
+            },
+            AtlasCall::Invite {
+                org_id: ORG_ID.to_string(),
+                username: NEW_USERNAME.to_string(),
+            },
+        ]
+    );
+    assert!(harness.atlas.user(ORG_ID, &user_id).is_some());
+}
+
+#[tokio::test]
+async fn migration_adopts_user_already_in_new_org() {
+    let harness = Harness::with_fake(migrate_config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let new_user_id = harness
+        .atlas
+        .add_user(ORG_ID, NEW_USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let atlas_user = harness.atlas_user_with_spec(renamed_spec(), 2, Some(applied_status(&user_id)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.calls(),
+        [
+            AtlasCall::FindByUsername {
+                org_id: ORG_ID.to_string(),
+                username: NEW_USERNAME.to_string(),
+            },
+            delete(&user_id),
+        ]
+    );
+    let status = harness.status();
+    assert_eq!(status.user_id.as_deref(), Some(new_user_id.as_str()));
+    assert_eq!(status.origin, Some(UserOrigin::Adopted));
+}
+
+#[tokio::test]
+async fn failed_removal_of_previous_membership_is_retried_without_migrating_again() {
+    let config = AtlasUserConfig {
+        adoption_policy: AdoptionPolicy::Never,
+        ..migrate_config()
+    };
+    let harness = Harness::with_fake(config);
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    harness
+        .atlas
+        .fail_next_call(|call| matches!(call, AtlasCall::Delete { .. }), server_error());
+    let atlas_user = harness.atlas_user_with_spec(renamed_spec(), 2, Some(applied_status(&user_id)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    let status = harness.status();
+    let new_user_id = status.user_id.clone().expect("new user recorded");
+    assert_ne!(&*new_user_id, user_id.as_str());
+    assert_eq!(status.origin, Some(UserOrigin::Created));
+    assert_eq!(status.applied_identity, Some(AppliedIdentity::of(&renamed_spec())));
+    assert_eq!(
+        status.previous_membership,
+        Some(PreviousMembership {
+            org_id: ORG_ID.to_string(),
+            user_id: user_id.clone(),
+            connection_secret_ref: None,
+        })
+    );
+    assert!(harness.atlas.user(ORG_ID, &user_id).is_some());
+
+    let atlas_user = harness.atlas_user_with_spec(renamed_spec(), 2, Some(harness.status()));
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    let new_username = AtlasCall::FindByUsername {
+        org_id: ORG_ID.to_string(),
+        username: NEW_USERNAME.to_string(),
+    };
+    let new_invite = AtlasCall::Invite {
+        org_id: ORG_ID.to_string(),
+        username: NEW_USERNAME.to_string(),
+    };
+    assert_eq!(
+        harness.atlas.calls(),
+        [new_username, new_invite, delete(&user_id), delete(&user_id)]
+    );
+    let status = harness.status();
+    assert_eq!(status.user_id, Some(new_user_id));
+    assert_eq!(status.origin, Some(UserOrigin::Created));
+    assert_eq!(status.previous_membership, None);
+    assert!(harness.atlas.user(ORG_ID, &user_id).is_none());
+}
+
+#[tokio::test]
+async fn cleanup_deletes_user_from_applied_org() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let spec = AtlasUserSpec {
+        org_id: NEW_ORG_ID.to_string(),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 2, Some(applied_status(&user_id)));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [delete(&user_id)]);
+}
+
+/// An error Atlas answers with while it is unavailable
+fn server_error() -> Error {
+    Error::Api {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        error: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn pending_invitation_is_recorded() {
+    let harness = Harness::with_fake(config());
+
+    harness.context.handle_apply(harness.atlas_user(1, None)).await.unwrap();
+
+    let status = harness.status();
+    let user = harness.atlas.user(ORG_ID, status.user_id.as_deref().unwrap()).unwrap();
+    assert_eq!(status.invitation_created_at, user.invitation_created_at);
+    assert_eq!(status.invitation_expires_at, user.invitation_expires_at);
+    assert!(status.invitation_expires_at.is_some());
+}
+
+#[tokio::test]
+async fn expired_invitation_is_replaced() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Pending, spec().roles);
+    harness.atlas.expire_invitation(ORG_ID, &user_id);
+    let atlas_user = harness.atlas_user(1, Some(applied_status(&user_id)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get(&user_id), delete(&user_id), invite()]);
+    let status = harness.status();
+    let new_user_id = status.user_id.clone().unwrap();
+    assert_ne!(&*new_user_id, user_id.as_str());
+    assert_eq!(status.reinvitations, 1);
+    assert_eq!(
+        status.invitation_expires_at,
+        harness.atlas.user(ORG_ID, &new_user_id).unwrap().invitation_expires_at
+    );
+    assert_eq!(
+        harness.condition(ConditionType::Invited),
+        (ConditionStatus::True, "Reinvited".to_string())
+    );
+    assert_eq!(harness.kube.event_reasons(), ["Reinvited"]);
+}
+
+#[tokio::test]
+async fn failed_reinvitation_is_sent_again() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Pending, spec().roles);
+    harness.atlas.expire_invitation(ORG_ID, &user_id);
+    harness
+        .atlas
+        .fail_next_call(|call| matches!(call, AtlasCall::Invite { .. }), server_error());
+
+    harness
+        .context
+        .handle_apply(harness.atlas_user(1, Some(applied_status(&user_id))))
+        .await
+        .unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get(&user_id), delete(&user_id), invite()]);
+    let status = harness.status();
+    assert_eq!(status.user_id, None);
+    assert!(status.reinvitation_pending);
+    assert_eq!(status.reinvitations, 1);
+
+    let atlas_user = harness.atlas_user(1, Some(harness.status()));
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.calls(),
+        [get(&user_id), delete(&user_id), invite(), invite()]
+    );
+    let status = harness.status();
+    assert!(status.user_id.is_some());
+    assert!(!status.reinvitation_pending);
+    assert_eq!(status.reinvitations, 1);
+    assert_eq!(status.deleted_externally_at, None);
+    assert_eq!(harness.kube.event_reasons(), ["ReconcileFailed", "Reinvited"]);
+}
+
+#[tokio::test]
+async fn expired_invitation_is_only_reported_after_max_reinvitations() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Pending, spec().roles);
+    harness.atlas.expire_invitation(ORG_ID, &user_id);
+    let status = AtlasUserStatus {
+        reinvitations: 3,
+        ..applied_status(&user_id)
+    };
+
+    harness
+        .context
+        .handle_apply(harness.atlas_user(1, Some(status)))
+        .await
+        .unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get(&user_id)]);
+    assert_eq!(harness.status().user_id.as_deref(), Some(user_id.as_str()));
+    assert_eq!(
+        harness.condition(ConditionType::Invited),
+        (ConditionStatus::False, "InvitationExpired".to_string())
+    );
+    assert_eq!(
+        harness.condition(ConditionType::Ready),
+        (ConditionStatus::False, "InvitationExpired".to_string())
+    );
+    assert_eq!(harness.kube.event_reasons(), ["InvitationExpired"]);
+
+    let atlas_user = harness.atlas_user(1, Some(harness.status()));
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.kube.event_reasons(),
+        ["InvitationExpired"],
+        "expiry reported once"
+    );
+}
+
+#[tokio::test]
+async fn expired_invitation_is_not_replaced_when_reinvitations_are_disabled() {
+    let config = AtlasUserConfig {
+        max_reinvitations: 0,
+        ..config()
+    };
+    let harness = Harness::with_fake(config);
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Pending, spec().roles);
+    harness.atlas.expire_invitation(ORG_ID, &user_id);
+
+    harness
+        .context
+        .handle_apply(harness.atlas_user(1, Some(applied_status(&user_id))))
+        .await
+        .unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get(&user_id)]);
+}
+
+/// Status of a user that was removed from Atlas, as no user is stored by the fake
+fn deleted_user_status() -> AtlasUserStatus {
+    applied_status("000000000000000000000042")
+}
+
+#[tokio::test]
+async fn external_deletion_fails_by_default_until_policy_changes() {
+    let harness = Harness::with_fake(config());
+
+    let action = harness
+        .context
+        .handle_apply(harness.atlas_user(1, Some(deleted_user_status())))
+        .await
+        .unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), [get("000000000000000000000042")]);
+    let status = harness.status();
+    assert_eq!(status.user_id, None);
+    assert!(status.deleted_externally_at.is_some());
+    assert_eq!(status.membership_status, Some(UserOrgMembershipStatus::Deleted));
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "UserDeletedExternally".to_string())
+    );
+
+    let atlas_user = harness.atlas_user(2, Some(harness.status()));
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), [get("000000000000000000000042")], "no lookup");
+    assert_eq!(harness.status().deleted_externally_at, status.deleted_externally_at);
+    assert_eq!(harness.kube.event_reasons(), ["DeletedExternally"]);
+}
+
+#[tokio::test]
+async fn external_deletion_recreates_user() {
+    let config = AtlasUserConfig {
+        on_external_deletion: ExternalDeletionPolicy::Recreate,
+        ..config()
+    };
+    let harness = Harness::with_fake(config);
+
+    harness
+        .context
+        .handle_apply(harness.atlas_user(1, Some(deleted_user_status())))
+        .await
+        .unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get("000000000000000000000042"), invite()]);
+    let status = harness.status();
+    assert!(status
+        .user_id
+        .is_some_and(|user_id| &*user_id != "000000000000000000000042"));
+    assert_eq!(status.deleted_externally_at, None);
+    assert_eq!(status.error, None);
+    assert_eq!(harness.kube.event_reasons(), ["DeletedExternally", "Invited"]);
+}
+
+#[tokio::test]
+async fn external_deletion_orphans_user_per_spec() {
+    let harness = Harness::with_fake(config());
+    let spec = AtlasUserSpec {
+        on_external_deletion: Some(ExternalDeletionPolicy::Orphan),
+        ..spec()
+    };
+
+    harness
+        .context
+        .handle_apply(harness.atlas_user_with_spec(spec.clone(), 1, Some(deleted_user_status())))
+        .await
+        .unwrap();
+
+    let status = harness.status();
+    assert_eq!(status.user_id, None);
+    assert_eq!(status.error, None);
+    assert_eq!(
+        harness.condition(ConditionType::Ready),
+        (ConditionStatus::False, "UserOrphaned".to_string())
+    );
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::False, "UserOrphaned".to_string())
+    );
+
+    let atlas_user = harness.atlas_user_with_spec(spec, 2, Some(status));
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), [get("000000000000000000000042")]);
+    assert_eq!(harness.kube.event_reasons(), ["DeletedExternally"]);
+}
+
+#[tokio::test]
+async fn externally_deleted_user_is_recreated_once_policy_changes() {
+    let harness = Harness::with_fake(config());
+    harness
+        .context
+        .handle_apply(harness.atlas_user(1, Some(deleted_user_status())))
+        .await
+        .unwrap();
+
+    let spec = AtlasUserSpec {
+        on_external_deletion: Some(ExternalDeletionPolicy::Recreate),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 2, Some(harness.status()));
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [get("000000000000000000000042"), invite()]);
+    let status = harness.status();
+    assert!(status.user_id.is_some());
+    assert_eq!(status.deleted_externally_at, None);
+    assert_eq!(status.error, None);
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::False, "ReconcileSucceeded".to_string())
+    );
+}
+
+#[tokio::test]
+async fn cleanup_of_externally_deleted_user_makes_no_calls() {
+    let harness = Harness::with_fake(config());
+    harness
+        .context
+        .handle_apply(harness.atlas_user(1, Some(deleted_user_status())))
+        .await
+        .unwrap();
+
+    let action = harness
+        .context
+        .handle_cleanup(harness.atlas_user(1, Some(harness.status())))
+        .await
+        .unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), [get("000000000000000000000042")]);
+}
+
+#[tokio::test]
+async fn cleanup_of_missing_user_succeeds() {
+    let harness = Harness::with_fake(config());
+
+    let action = harness
+        .context
+        .handle_cleanup(harness.atlas_user(1, Some(deleted_user_status())))
+        .await
+        .unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), [delete("000000000000000000000042")]);
+}
+
+#[tokio::test]
+async fn cleanup_retains_user_per_spec() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let spec = AtlasUserSpec {
+        deletion_policy: Some(DeletionPolicy::Retain),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 1, Some(applied_status(&user_id)));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), []);
+    assert_eq!(
+        harness.condition(ConditionType::Ready),
+        (ConditionStatus::False, "UserRetained".to_string())
+    );
+}
+
+#[tokio::test]
+async fn spec_delete_policy_overrides_safe_to_delete() {
+    let config = AtlasUserConfig {
+        safe_to_delete: false,
+        ..config()
+    };
+    let harness = Harness::with_fake(config);
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let spec = AtlasUserSpec {
+        deletion_policy: Some(DeletionPolicy::Delete),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 1, Some(applied_status(&user_id)));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), [delete(&user_id)]);
+}
+
+#[tokio::test]
+async fn deletion_policy_annotation_overrides_spec() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let spec = AtlasUserSpec {
+        deletion_policy: Some(DeletionPolicy::Delete),
+        ..spec()
+    };
+    let mut atlas_user = AtlasUser::clone(&harness.atlas_user_with_spec(spec, 1, Some(applied_status(&user_id))));
+    atlas_user
+        .annotations_mut()
+        .insert(DELETION_POLICY_ANNOTATION.to_string(), "Retain".to_string());
+
+    harness.context.handle_cleanup(Arc::new(atlas_user)).await.unwrap();
+
+    assert_eq!(harness.atlas.calls(), []);
+}
+
+#[tokio::test]
+async fn invalid_deletion_policy_annotation_fails_cleanup() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let mut atlas_user = AtlasUser::clone(&harness.atlas_user(1, Some(applied_status(&user_id))));
+    atlas_user
+        .annotations_mut()
+        .insert(DELETION_POLICY_ANNOTATION.to_string(), "Retian".to_string());
+
+    let result = harness.context.handle_cleanup(Arc::new(atlas_user)).await;
+
+    assert!(result.is_err());
+    assert_eq!(harness.atlas.calls(), []);
+}
+
+#[tokio::test]
+async fn cleanup_removes_only_project_roles_of_resource() {
+    const OTHER_GROUP_ID: &str = "7b8c9d0e1f5f1b2c3d4e5f6a";
+    let harness = Harness::with_fake(config());
+    let mut roles = spec().roles;
+    roles.group_role_assignments[0]
+        .group_roles
+        .push(GroupRoleName::GroupOwner);
+    roles.group_role_assignments.push(GroupRoleAssignment {
+        group_id: OTHER_GROUP_ID.to_string(),
+        group_roles: vec![GroupRoleName::GroupReadOnly],
+    });
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, roles);
+    let spec = AtlasUserSpec {
+        deletion_policy: Some(DeletionPolicy::RemoveProjectRolesOnly),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 1, Some(applied_status(&user_id)));
+
+    let action = harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(harness.atlas.calls(), [get(&user_id), update(&user_id)]);
+    let user = harness.atlas.user(ORG_ID, &user_id).expect("user kept");
+    assert_eq!(user.roles.org_roles, [OrgRoleName::OrgMember]);
+    assert_eq!(
+        user.roles.group_role_assignments,
+        [
+            GroupRoleAssignment {
+                group_id: GROUP_ID.to_string(),
+                group_roles: vec![GroupRoleName::GroupOwner],
+            },
+            GroupRoleAssignment {
+                group_id: OTHER_GROUP_ID.to_string(),
+                group_roles: vec![GroupRoleName::GroupReadOnly],
+            },
+        ]
+    );
+    assert_eq!(harness.kube.event_reasons(), ["RolesRemoved"]);
+}
+
+#[tokio::test]
+async fn removing_project_roles_drops_emptied_assignments() {
+    let harness = Harness::with_fake(config());
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, UserOrgMembershipStatus::Active, spec().roles);
+    let spec = AtlasUserSpec {
+        deletion_policy: Some(DeletionPolicy::RemoveProjectRolesOnly),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec, 1, Some(applied_status(&user_id)));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    let user = harness.atlas.user(ORG_ID, &user_id).expect("user kept");
+    assert_eq!(user.roles.group_role_assignments, []);
+    assert_eq!(user.roles.org_roles, [OrgRoleName::OrgMember]);
+}
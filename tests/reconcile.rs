@@ -0,0 +1,441 @@
+mod support;
+
+use std::time::Duration;
+
+use kube::runtime::controller::Action;
+use kuberator::Context;
+use mongodb_atlas_k8s_operator::config::AtlasUserConfig;
+use mongodb_atlas_k8s_operator::config::DriftPolicy;
+use mongodb_atlas_k8s_operator::config::IdentityChangePolicy;
+use mongodb_atlas_k8s_operator::crd::AppliedIdentity;
+use mongodb_atlas_k8s_operator::crd::AtlasUserSpec;
+use mongodb_atlas_k8s_operator::crd::AtlasUserStatus;
+use mongodb_atlas_k8s_operator::crd::ConditionStatus;
+use mongodb_atlas_k8s_operator::crd::ConditionType;
+use mongodb_atlas_k8s_operator::crd::SecretReference;
+use mongodb_atlas_k8s_operator::crd::UserOrgMembershipStatus;
+use mongodb_atlas_k8s_operator::crd::UserOrigin;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::support::config;
+use crate::support::connection_secret;
+use crate::support::reconciled_status;
+use crate::support::spec;
+use crate::support::spec_roles;
+use crate::support::Harness;
+use crate::support::ORG_ID;
+use crate::support::REQUEUE;
+use crate::support::USERNAME;
+
+#[tokio::test]
+async fn apply_invites_new_user() {
+    let harness = Harness::new(config()).await;
+    let atlas_user = harness.atlas_user(1, None);
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(REQUEUE));
+    assert_eq!(
+        harness.atlas.requests(),
+        [
+            format!("GET /orgs/{ORG_ID}/users"),
+            format!("POST /orgs/{ORG_ID}/users")
+        ]
+    );
+    let status = harness.status();
+    let user_id = status.user_id.expect("user ID recorded");
+    let user = harness.atlas.user(ORG_ID, &user_id).expect("user invited");
+    assert_eq!(user["username"], USERNAME);
+    assert_eq!(user["roles"], spec_roles());
+    assert_eq!(status.membership_status, Some(UserOrgMembershipStatus::Pending));
+    assert_eq!(status.observed_generation, Some(1));
+    assert_eq!(
+        harness.condition(ConditionType::Invited),
+        (ConditionStatus::True, "InvitationPending".to_string())
+    );
+    assert_eq!(harness.kube.event_reasons(), ["Invited"]);
+}
+
+#[tokio::test]
+async fn apply_records_atlas_requests_and_membership_in_metrics() {
+    let harness = Harness::new(config()).await;
+    let atlas_user = harness.atlas_user(1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    let metrics = harness.metrics.encode().unwrap();
+    let users = r#"endpoint="/orgs/{orgId}/users""#;
+    for expected in [
+        format!(r#"atlas_operator_atlas_requests_total{{{users},method="GET",status="200"}} 1"#),
+        format!(r#"atlas_operator_atlas_requests_total{{{users},method="POST",status="201"}} 1"#),
+        format!(r#"atlas_operator_atlas_request_duration_seconds_count{{{users},method="GET",status="200"}} 1"#),
+        format!(r#"atlas_operator_atlas_request_duration_seconds_count{{{users},method="POST",status="201"}} 1"#),
+        r#"atlas_operator_managed_users{membership_status="ACTIVE"} 0"#.to_string(),
+        r#"atlas_operator_managed_users{membership_status="PENDING"} 1"#.to_string(),
+        r#"atlas_operator_reconciliations_total{action="apply",outcome="success"} 1"#.to_string(),
+    ] {
+        assert!(
+            metrics.lines().any(|line| line == expected),
+            "{expected} not in\n{metrics}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn apply_syncs_status_of_existing_user() {
+    let harness = Harness::new(config()).await;
+    let user_id = harness.atlas.add_user(ORG_ID, USERNAME, "ACTIVE", spec_roles());
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(REQUEUE));
+    assert_eq!(
+        harness.atlas.requests(),
+        [format!("GET /orgs/{ORG_ID}/users/{user_id}")]
+    );
+    assert_eq!(
+        harness.status().membership_status,
+        Some(UserOrgMembershipStatus::Active)
+    );
+    assert_eq!(harness.status().drift, None);
+    assert_eq!(
+        harness.condition(ConditionType::Ready),
+        (ConditionStatus::True, "UserActive".to_string())
+    );
+}
+
+#[tokio::test]
+async fn apply_updates_user_when_spec_changed() {
+    let harness = Harness::new(config()).await;
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, "ACTIVE", json!({ "orgRoles": ["ORG_READ_ONLY"] }));
+    let atlas_user = harness.atlas_user(2, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.requests(),
+        [format!("PATCH /orgs/{ORG_ID}/users/{user_id}")]
+    );
+    assert_eq!(harness.atlas.user(ORG_ID, &user_id).unwrap()["roles"], spec_roles());
+    assert_eq!(harness.status().observed_generation, Some(2));
+    assert_eq!(harness.kube.event_reasons(), ["Updated"]);
+}
+
+#[tokio::test]
+async fn apply_corrects_drift() {
+    let harness = Harness::new(config()).await;
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, "ACTIVE", json!({ "orgRoles": ["ORG_OWNER"] }));
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.requests(),
+        [
+            format!("GET /orgs/{ORG_ID}/users/{user_id}"),
+            format!("PATCH /orgs/{ORG_ID}/users/{user_id}"),
+        ]
+    );
+    assert_eq!(harness.atlas.user(ORG_ID, &user_id).unwrap()["roles"], spec_roles());
+    assert_eq!(
+        harness.status().drift,
+        Some(vec![
+            "roles.orgRoles".to_string(),
+            "roles.groupRoleAssignments".to_string()
+        ])
+    );
+    assert_eq!(
+        harness.condition(ConditionType::Drifted),
+        (ConditionStatus::True, "DriftCorrected".to_string())
+    );
+}
+
+#[tokio::test]
+async fn apply_only_reports_drift_with_report_only_policy() {
+    let config = AtlasUserConfig {
+        drift_policy: DriftPolicy::ReportOnly,
+        ..config()
+    };
+    let harness = Harness::new(config).await;
+    let user_id = harness
+        .atlas
+        .add_user(ORG_ID, USERNAME, "ACTIVE", json!({ "orgRoles": ["ORG_OWNER"] }));
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.requests(),
+        [format!("GET /orgs/{ORG_ID}/users/{user_id}")]
+    );
+    assert_eq!(
+        harness.condition(ConditionType::Ready),
+        (ConditionStatus::False, "DriftDetected".to_string())
+    );
+}
+
+#[tokio::test]
+async fn apply_adopts_user_already_in_org() {
+    let harness = Harness::new(config()).await;
+    let user_id = harness.atlas.add_user(ORG_ID, USERNAME, "ACTIVE", spec_roles());
+    let atlas_user = harness.atlas_user(1, None);
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(Duration::from_secs(1)));
+    assert_eq!(harness.atlas.requests(), [format!("GET /orgs/{ORG_ID}/users")]);
+    let status = harness.status();
+    assert_eq!(status.user_id.as_deref(), Some(user_id.as_str()));
+    assert_eq!(status.origin, Some(UserOrigin::Adopted));
+    assert_eq!(harness.atlas.user_count(ORG_ID), 1);
+    assert_eq!(harness.kube.event_reasons(), ["Adopted"]);
+}
+
+#[tokio::test]
+async fn apply_finds_user_by_username_across_pages() {
+    let harness = Harness::new(config()).await;
+    for i in 0..600 {
+        harness
+            .atlas
+            .add_user(ORG_ID, &format!("user{i}@example.com"), "ACTIVE", json!({}));
+    }
+    let user_id = harness.atlas.add_user(ORG_ID, USERNAME, "ACTIVE", spec_roles());
+    harness.atlas.ignore_username_filter();
+    let atlas_user = harness.atlas_user(2, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(harness.atlas.requests(), vec![format!("GET /orgs/{ORG_ID}/users"); 2]);
+    assert_eq!(harness.status().user_id.as_deref(), Some(user_id.as_str()));
+}
+
+#[tokio::test]
+async fn apply_retries_rate_limited_requests() {
+    let harness = Harness::new(config()).await;
+    harness.atlas.rate_limit_next(2);
+    let atlas_user = harness.atlas_user(1, None);
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(
+        harness.atlas.requests(),
+        [
+            format!("GET /orgs/{ORG_ID}/users"),
+            format!("GET /orgs/{ORG_ID}/users"),
+            format!("GET /orgs/{ORG_ID}/users"),
+            format!("POST /orgs/{ORG_ID}/users")
+        ]
+    );
+    assert_eq!(harness.atlas.user_count(ORG_ID), 1);
+}
+
+#[tokio::test]
+async fn apply_backs_off_when_rate_limited_beyond_retries() {
+    let harness = Harness::new(config()).await;
+    harness.atlas.rate_limit_next(3);
+    let atlas_user = harness.atlas_user(1, None);
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(Duration::from_secs(30)));
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "RateLimited".to_string())
+    );
+}
+
+#[tokio::test]
+async fn apply_does_not_retry_invalid_spec() {
+    let harness = Harness::new(config()).await;
+    let user_id = harness.atlas.add_user(ORG_ID, USERNAME, "ACTIVE", spec_roles());
+    harness
+        .atlas
+        .fail_next(StatusCode::BAD_REQUEST, "INVALID_ROLE_FOR_USER");
+    let atlas_user = harness.atlas_user(2, Some(reconciled_status(&user_id, 1)));
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(
+        harness.atlas.requests(),
+        [format!("PATCH /orgs/{ORG_ID}/users/{user_id}")]
+    );
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "InvalidSpec".to_string())
+    );
+    assert_eq!(harness.kube.event_reasons(), ["ReconcileFailed"]);
+}
+
+#[tokio::test]
+async fn apply_requeues_when_atlas_resource_not_found() {
+    let harness = Harness::new(config()).await;
+    harness.atlas.fail_next(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND");
+    let atlas_user = harness.atlas_user(1, None);
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(REQUEUE));
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "PermanentFailure".to_string())
+    );
+}
+
+#[tokio::test]
+async fn apply_backs_off_increasingly_on_server_errors() {
+    let harness = Harness::new(config()).await;
+    let mut actions = Vec::new();
+    for _ in 0..2 {
+        for _ in 0..3 {
+            harness
+                .atlas
+                .fail_next(StatusCode::SERVICE_UNAVAILABLE, "UNEXPECTED_ERROR");
+        }
+        let atlas_user = harness.atlas_user(2, None);
+        actions.push(harness.context.handle_apply(atlas_user).await.unwrap());
+    }
+
+    assert_eq!(
+        actions,
+        [
+            Action::requeue(Duration::from_secs(5)),
+            Action::requeue(Duration::from_secs(10))
+        ]
+    );
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "ReconcileFailed".to_string())
+    );
+}
+
+#[tokio::test]
+async fn apply_reports_user_deleted_externally() {
+    let harness = Harness::new(config()).await;
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status("000000000000000000000042", 1)));
+
+    harness.context.handle_apply(atlas_user).await.unwrap();
+
+    let status = harness.status();
+    assert_eq!(status.user_id, None);
+    assert_eq!(status.error.as_deref(), Some("User was deleted externally from Atlas"));
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "UserDeletedExternally".to_string())
+    );
+    assert_eq!(harness.kube.event_reasons(), ["DeletedExternally"]);
+}
+
+#[tokio::test]
+async fn apply_reports_user_deleted_externally_when_spec_changed() {
+    let harness = Harness::new(config()).await;
+    let atlas_user = harness.atlas_user(2, Some(reconciled_status("000000000000000000000042", 1)));
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(
+        harness.atlas.requests(),
+        [format!("PATCH /orgs/{ORG_ID}/users/000000000000000000000042")]
+    );
+    let status = harness.status();
+    assert_eq!(status.user_id, None);
+    assert_eq!(status.observed_generation, Some(2));
+    assert!(status.deleted_externally_at.is_some());
+    assert_eq!(
+        harness.condition(ConditionType::Error),
+        (ConditionStatus::True, "UserDeletedExternally".to_string())
+    );
+    assert_eq!(harness.kube.event_reasons(), ["DeletedExternally"]);
+}
+
+#[tokio::test]
+async fn migration_between_orgs_removes_previous_membership_with_its_secret() {
+    const NEW_ORG_ID: &str = "0f1e2d3c4b5a69788796a5b4";
+    let harness = Harness::new(AtlasUserConfig {
+        identity_change_policy: IdentityChangePolicy::Migrate,
+        ..config()
+    })
+    .await;
+    harness
+        .kube
+        .insert_secret(&connection_secret("org-credentials", "org-token"));
+    harness
+        .kube
+        .insert_secret(&connection_secret("new-org-credentials", "new-org-token"));
+    harness.atlas.require_token(ORG_ID, "org-token");
+    harness.atlas.require_token(NEW_ORG_ID, "new-org-token");
+    let user_id = harness.atlas.add_user(ORG_ID, USERNAME, "ACTIVE", spec_roles());
+    let secret_ref = |name: &str| Some(SecretReference { name: name.to_string() });
+    let status = AtlasUserStatus {
+        applied_identity: Some(AppliedIdentity {
+            org_id: ORG_ID.to_string(),
+            username: USERNAME.to_string(),
+            connection_secret_ref: secret_ref("org-credentials"),
+        }),
+        ..reconciled_status(&user_id, 1)
+    };
+    let spec = AtlasUserSpec {
+        org_id: NEW_ORG_ID.to_string(),
+        connection_secret_ref: secret_ref("new-org-credentials"),
+        ..spec()
+    };
+    let atlas_user = harness.atlas_user_with_spec(spec.clone(), 2, Some(status));
+
+    let action = harness.context.handle_apply(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::requeue(REQUEUE));
+    assert_eq!(harness.atlas.user_count(ORG_ID), 0);
+    assert_eq!(harness.atlas.user_count(NEW_ORG_ID), 1);
+    let status = harness.status();
+    assert_eq!(status.applied_identity, Some(AppliedIdentity::of(&spec)));
+    assert_eq!(status.previous_membership, None);
+    assert_eq!(harness.kube.event_reasons(), ["Migrated", "Removed"]);
+}
+
+#[tokio::test]
+async fn cleanup_removes_user_from_org() {
+    let harness = Harness::new(config()).await;
+    let user_id = harness.atlas.add_user(ORG_ID, USERNAME, "ACTIVE", spec_roles());
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    let action = harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    assert_eq!(
+        harness.atlas.requests(),
+        [format!("DELETE /orgs/{ORG_ID}/users/{user_id}")]
+    );
+    assert_eq!(harness.atlas.user_count(ORG_ID), 0);
+    assert_eq!(
+        harness.status().membership_status,
+        Some(UserOrgMembershipStatus::Deleted)
+    );
+    assert_eq!(harness.kube.event_reasons(), ["Removed"]);
+}
+
+#[tokio::test]
+async fn cleanup_retains_user_unless_safe_to_delete() {
+    let config = AtlasUserConfig {
+        safe_to_delete: false,
+        ..config()
+    };
+    let harness = Harness::new(config).await;
+    let user_id = harness.atlas.add_user(ORG_ID, USERNAME, "ACTIVE", spec_roles());
+    let atlas_user = harness.atlas_user(1, Some(reconciled_status(&user_id, 1)));
+
+    harness.context.handle_cleanup(atlas_user).await.unwrap();
+
+    assert!(harness.atlas.requests().is_empty());
+    assert_eq!(harness.atlas.user_count(ORG_ID), 1);
+    assert_eq!(
+        harness.condition(ConditionType::Ready),
+        (ConditionStatus::False, "UserRetained".to_string())
+    );
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::extract::State;
+use axum::http::header;
+use axum::http::Method;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Json;
+use axum::Router;
+use chrono::Duration;
+use chrono::Utc;
+use serde_json::json;
+use serde_json::Value;
+use tokio::net::TcpListener;
+use url::Url;
+
+const API_PREFIX: &str = "/api/atlas/v2";
+
+/// In-process mock of the Atlas Admin API v2 organization user endpoints.
+///
+/// Users are kept per organization. Responses can be made to fail with Atlas error bodies or `429 Too Many
+/// Requests`, and every request is recorded for assertions.
+#[derive(Clone, Default)]
+pub struct MockAtlas {
+    state: Arc<Mutex<AtlasState>>,
+}
+
+#[derive(Default)]
+struct AtlasState {
+    users: HashMap<String, Vec<Value>>,
+    failures: VecDeque<Failure>,
+    requests: Vec<String>,
+    next_id: u64,
+    ignore_username_filter: bool,
+    /// Access tokens accepted per organization, any token is accepted for other organizations
+    org_tokens: HashMap<String, String>,
+}
+
+/// A response replacing the next regular response of the mock
+struct Failure {
+    status: StatusCode,
+    error_code: &'static str,
+    retry_after: Option<u64>,
+}
+
+impl MockAtlas {
+    /// Starts the mock on a random local port and returns it along with its base URL
+    pub async fn start() -> (Self, Url) {
+        let mock = MockAtlas::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bindable local port");
+        let base_url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+        let router = Router::new().fallback(handle).with_state(mock.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        (mock, base_url)
+    }
+
+    /// Adds a user to the organization and returns its ID
+    pub fn add_user(&self, org_id: &str, username: &str, membership_status: &str, roles: Value) -> String {
+        let mut state = self.state.lock().unwrap();
+        let id = next_id(&mut state);
+        let user = json!({
+            "id": id,
+            "username": username,
+            "orgMembershipStatus": membership_status,
+            "roles": roles,
+            "teamIds": [],
+        });
+        state.users.entry(org_id.to_string()).or_default().push(user);
+        id
+    }
+
+    /// The user as currently stored by the mock
+    pub fn user(&self, org_id: &str, user_id: &str) -> Option<Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .users
+            .get(org_id)?
+            .iter()
+            .find(|user| user["id"] == user_id)
+            .cloned()
+    }
+
+    /// Number of users in the organization
+    pub fn user_count(&self, org_id: &str) -> usize {
+        self.state.lock().unwrap().users.get(org_id).map_or(0, Vec::len)
+    }
+
+    /// Answers the next request with the given status and Atlas error code
+    pub fn fail_next(&self, status: StatusCode, error_code: &'static str) {
+        self.state.lock().unwrap().failures.push_back(Failure {
+            status,
+            error_code,
+            retry_after: None,
+        });
+    }
+
+    /// Answers the next `count` requests with `429 Too Many Requests` and an immediate `Retry-After`
+    pub fn rate_limit_next(&self, count: usize) {
+        let mut state = self.state.lock().unwrap();
+        for _ in 0..count {
+            state.failures.push_back(Failure {
+                status: StatusCode::TOO_MANY_REQUESTS,
+                error_code: "RATE_LIMITED",
+                retry_after: Some(0),
+            });
+        }
+    }
+
+    /// Only accepts the given access token for requests to the organization
+    pub fn require_token(&self, org_id: &str, access_token: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.org_tokens.insert(org_id.to_string(), access_token.to_string());
+    }
+
+    /// Lists all users regardless of the `username` query parameter, so that lookups page through them
+    pub fn ignore_username_filter(&self) {
+        self.state.lock().unwrap().ignore_username_filter = true;
+    }
+
+    /// Method and path of every request received so far, without the API prefix and query
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+async fn handle(State(mock): State<MockAtlas>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .expect("readable request body");
+    let path = parts.uri.path().trim_start_matches(API_PREFIX).to_string();
+    let query: HashMap<String, String> = parts
+        .uri
+        .query()
+        .map(|query| url::form_urlencoded::parse(query.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+    let mut state = mock.state.lock().unwrap();
+    state.requests.push(format!("{} {}", parts.method, path));
+
+    let Some(authorization) = parts.headers.get(header::AUTHORIZATION) else {
+        return error(StatusCode::UNAUTHORIZED, "NOT_ATLAS_ADMIN", None);
+    };
+    if let ["orgs", org_id, ..] = segments.as_slice() {
+        let required = state.org_tokens.get(*org_id).map(|token| format!("Bearer {token}"));
+        if required.is_some_and(|required| authorization != required.as_str()) {
+            return error(StatusCode::UNAUTHORIZED, "NOT_ORG_MEMBER", None);
+        }
+    }
+    if let Some(failure) = state.failures.pop_front() {
+        return error(failure.status, failure.error_code, failure.retry_after);
+    }
+
+    match (parts.method, segments.as_slice()) {
+        (Method::GET, ["orgs"]) => Json(json!({ "results": [], "totalCount": 0 })).into_response(),
+        (Method::POST, ["orgs", org_id, "users"]) => {
+            let invite: Value = serde_json::from_slice(&body).expect("JSON invite");
+            let username = invite["username"].as_str().expect("invite with username").to_string();
+            let users = state.users.entry(org_id.to_string()).or_default();
+            if users.iter().any(|user| user["username"] == username.as_str()) {
+                return error(StatusCode::CONFLICT, "USER_ALREADY_IN_ORG", None);
+            }
+
+            let id = next_id(&mut state);
+            let user = json!({
+                "id": id,
+                "username": username,
+                "orgMembershipStatus": "PENDING",
+                "roles": invite["roles"],
+                "teamIds": invite["teamIds"],
+                "invitationCreatedAt": Utc::now(),
+                "invitationExpiresAt": Utc::now() + Duration::days(30),
+            });
+            state.users.entry(org_id.to_string()).or_default().push(user.clone());
+            (StatusCode::CREATED, Json(user)).into_response()
+        }
+        (Method::GET, ["orgs", org_id, "users"]) => {
+            let page_num: usize = query.get("pageNum").map_or(1, |page_num| page_num.parse().unwrap());
+            let items_per_page: usize = query.get("itemsPerPage").map_or(100, |items| items.parse().unwrap());
+            let username = query.get("username").filter(|_| !state.ignore_username_filter);
+            let users: Vec<&Value> = state
+                .users
+                .get(*org_id)
+                .into_iter()
+                .flatten()
+                .filter(|user| username.is_none_or(|username| user["username"] == username.as_str()))
+                .collect();
+            let page: Vec<&Value> = users
+                .iter()
+                .skip((page_num - 1) * items_per_page)
+                .take(items_per_page)
+                .copied()
+                .collect();
+            Json(json!({ "results": page, "totalCount": users.len() })).into_response()
+        }
+        (Method::GET, ["orgs", org_id, "users", user_id]) => match find_user(&mut state, org_id, user_id) {
+            Some(user) => Json(user.clone()).into_response(),
+            None => error(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND", None),
+        },
+        (Method::PATCH, ["orgs", org_id, "users", user_id]) => {
+            let update: Value = serde_json::from_slice(&body).expect("JSON update");
+            match find_user(&mut state, org_id, user_id) {
+                Some(user) => {
+                    user["roles"] = update["roles"].clone();
+                    user["teamIds"] = update["teamIds"].clone();
+                    Json(user.clone()).into_response()
+                }
+                None => error(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND", None),
+            }
+        }
+        (Method::DELETE, ["orgs", org_id, "users", user_id]) => {
+            let users = state.users.entry(org_id.to_string()).or_default();
+            match users.iter().position(|user| user["id"] == *user_id) {
+                Some(index) => {
+                    users.remove(index);
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                None => error(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND", None),
+            }
+        }
+        (method, _) => panic!("unexpected Atlas request {method} {path}"),
+    }
+}
+
+fn next_id(state: &mut AtlasState) -> String {
+    state.next_id += 1;
+    format!("{:024x}", state.next_id)
+}
+
+fn find_user<'a>(state: &'a mut AtlasState, org_id: &str, user_id: &str) -> Option<&'a mut Value> {
+    state
+        .users
+        .get_mut(org_id)?
+        .iter_mut()
+        .find(|user| user["id"] == user_id)
+}
+
+/// An error response with an Atlas error body
+fn error(status: StatusCode, error_code: &str, retry_after: Option<u64>) -> Response {
+    let body = json!({
+        "error": status.as_u16(),
+        "errorCode": error_code,
+        "detail": format!("Mock error {error_code}"),
+        "reason": status.canonical_reason(),
+        "parameters": [],
+    });
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(retry_after) = retry_after {
+        response = response.header(header::RETRY_AFTER, retry_after);
+    }
+    response
+        .body(Body::from(body.to_string()))
+        .expect("valid error response")
+}
@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::extract::State;
+use axum::http::header;
+use axum::http::Method;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Json;
+use axum::Router;
+use digest_auth::AuthContext;
+use digest_auth::AuthorizationHeader;
+use digest_auth::HttpMethod;
+use serde_json::json;
+use tokio::net::TcpListener;
+use url::Url;
+
+pub const PUBLIC_KEY: &str = "public-key";
+pub const PRIVATE_KEY: &str = "private-key";
+pub const CLIENT_ID: &str = "client-id";
+pub const CLIENT_SECRET: &str = "client-secret";
+
+/// `Authorization` header of the token request with [`CLIENT_ID`] and [`CLIENT_SECRET`]
+const CLIENT_BASIC_AUTHORIZATION: &str = "Basic Y2xpZW50LWlkOmNsaWVudC1zZWNyZXQ=";
+
+/// In-process mock of the authentication of the Atlas Admin API.
+///
+/// `GET /api/atlas/v2/orgs` accepts HTTP Digest authentication with [`PUBLIC_KEY`] and [`PRIVATE_KEY`], verifying the
+/// response of the current nonce and rejecting reused nonce counts, as well as bearer tokens issued by
+/// `POST /api/oauth/token` to [`CLIENT_ID`] and [`CLIENT_SECRET`].
+#[derive(Clone)]
+pub struct MockAuth {
+    state: Arc<Mutex<AuthState>>,
+}
+
+struct AuthState {
+    requests: Vec<String>,
+    nonce: u32,
+    /// Highest nonce count accepted for the current nonce
+    nc: u32,
+    /// Nonce and nonce count of every accepted digest authorization
+    digests: Vec<(String, u32)>,
+    issued_tokens: u32,
+    valid_tokens: HashSet<String>,
+    expires_in: u64,
+}
+
+impl MockAuth {
+    /// Starts the mock on a random local port and returns it along with its base URL
+    pub async fn start() -> (Self, Url) {
+        let mock = MockAuth {
+            state: Arc::new(Mutex::new(AuthState {
+                requests: Vec::new(),
+                nonce: 1,
+                nc: 0,
+                digests: Vec::new(),
+                issued_tokens: 0,
+                valid_tokens: HashSet::new(),
+                expires_in: 3600,
+            })),
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bindable local port");
+        let base_url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+        let router = Router::new().fallback(handle).with_state(mock.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        (mock, base_url)
+    }
+
+    /// Issues a new nonce, so that authorizations with the previous one are rejected as stale
+    pub fn rotate_nonce(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.nonce += 1;
+        state.nc = 0;
+    }
+
+    /// Lifetime in seconds of the tokens issued from now on
+    pub fn set_expires_in(&self, expires_in: u64) {
+        self.state.lock().unwrap().expires_in = expires_in;
+    }
+
+    /// Rejects all tokens issued so far
+    pub fn revoke_tokens(&self) {
+        self.state.lock().unwrap().valid_tokens.clear();
+    }
+
+    /// Method, path and response status of every request received so far
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Nonce and nonce count of every accepted digest authorization
+    pub fn digests(&self) -> Vec<(String, u32)> {
+        self.state.lock().unwrap().digests.clone()
+    }
+}
+
+async fn handle(State(mock): State<MockAuth>, request: Request) -> Response {
+    let mut state = mock.state.lock().unwrap();
+    let path = request.uri().path().to_string();
+
+    let response = match (request.method(), path.as_str()) {
+        (&Method::POST, "/api/oauth/token") => state.issue_token(&request),
+        (&Method::GET, "/api/atlas/v2/orgs") => state.authenticate(&request),
+        (method, _) => panic!("unexpected Atlas request {method} {path}"),
+    };
+
+    let request = format!("{} {path} {}", request.method(), response.status().as_u16());
+    state.requests.push(request);
+    response
+}
+
+impl AuthState {
+    fn issue_token(&mut self, request: &Request) -> Response {
+        if authorization(request) != Some(CLIENT_BASIC_AUTHORIZATION) {
+            return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "invalid_client" }))).into_response();
+        }
+
+        self.issued_tokens += 1;
+        let access_token = format!("token-{}", self.issued_tokens);
+        self.valid_tokens.insert(access_token.clone());
+        Json(json!({
+            "access_token": access_token,
+            "expires_in": self.expires_in,
+            "token_type": "Bearer",
+        }))
+        .into_response()
+    }
+
+    fn authenticate(&mut self, request: &Request) -> Response {
+        let authorized = match authorization(request) {
+            Some(authorization) if authorization.starts_with("Bearer ") => {
+                self.valid_tokens.contains(authorization.trim_start_matches("Bearer "))
+            }
+            Some(authorization) if authorization.starts_with("Digest ") => {
+                let digest = AuthorizationHeader::parse(authorization).expect("valid digest authorization");
+                if digest.nonce != self.nonce() {
+                    return self.challenge(true);
+                }
+                self.accept_digest(request, digest)
+            }
+            _ => false,
+        };
+
+        match (authorized, authorization(request)) {
+            (true, _) => Json(json!({ "results": [], "totalCount": 0 })).into_response(),
+            (false, Some(authorization)) if authorization.starts_with("Bearer ") => unauthorized(None),
+            (false, _) => self.challenge(false),
+        }
+    }
+
+    /// Verifies the digest response for the current nonce, accepting every nonce count only once
+    fn accept_digest(&mut self, request: &Request, digest: AuthorizationHeader) -> bool {
+        let uri = request.uri().path_and_query().expect("request URI with path").as_str();
+        if digest.username != PUBLIC_KEY || digest.uri != uri || digest.nc <= self.nc {
+            return false;
+        }
+
+        let mut context = AuthContext::new_with_method(
+            PUBLIC_KEY,
+            PRIVATE_KEY,
+            uri,
+            Option::<&[u8]>::None,
+            HttpMethod::from(request.method().as_str()),
+        );
+        if let Some(cnonce) = digest.cnonce.as_deref() {
+            context.set_custom_cnonce(cnonce);
+        }
+        let mut expected = digest.clone();
+        expected.digest(&context);
+        if expected.response != digest.response {
+            return false;
+        }
+
+        self.nc = digest.nc;
+        self.digests.push((digest.nonce, digest.nc));
+        true
+    }
+
+    fn nonce(&self) -> String {
+        format!("nonce-{}", self.nonce)
+    }
+
+    fn challenge(&self, stale: bool) -> Response {
+        let challenge = format!(
+            r#"Digest realm="MMS Public API", domain="", nonce="{}", algorithm=MD5, qop="auth", stale={stale}"#,
+            self.nonce()
+        );
+        unauthorized(Some(challenge))
+    }
+}
+
+fn authorization(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// A `401 Unauthorized` response with an Atlas error body and the given digest challenge
+fn unauthorized(challenge: Option<String>) -> Response {
+    let body = json!({
+        "error": 401,
+        "errorCode": "UNAUTHORIZED",
+        "detail": "Mock error UNAUTHORIZED",
+        "reason": "Unauthorized",
+        "parameters": [],
+    });
+
+    let mut response = Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(challenge) = challenge {
+        response = response.header(header::WWW_AUTHENTICATE, challenge);
+    }
+    response
+        .body(Body::from(body.to_string()))
+        .expect("valid unauthorized response")
+}
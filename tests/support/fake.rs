@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::Duration;
+use chrono::Utc;
+use mongodb_atlas_k8s_operator::atlas::error::Error;
+use mongodb_atlas_k8s_operator::atlas::error::Result;
+use mongodb_atlas_k8s_operator::atlas::user_request::UserRequest;
+use mongodb_atlas_k8s_operator::atlas::user_response::UserResponse;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserApi;
+use mongodb_atlas_k8s_operator::crd::AtlasUserRoles;
+use mongodb_atlas_k8s_operator::crd::UserOrgMembershipStatus;
+
+/// An Atlas operation made through [`AtlasUserApi`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtlasCall {
+    Invite { org_id: String, username: String },
+    Update { org_id: String, user_id: String },
+    Delete { org_id: String, user_id: String },
+    Get { org_id: String, user_id: String },
+    FindByUsername { org_id: String, username: String },
+    VerifyCredentials,
+}
+
+/// Selects the calls a failure applies to
+pub type CallMatcher = fn(&AtlasCall) -> bool;
+
+/// In-memory [`AtlasUserApi`] that keeps users per organization and records every call made to it
+#[derive(Default)]
+pub struct FakeAtlasUserApi {
+    state: Mutex<FakeState>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    users: Vec<(String, UserResponse)>,
+    failures: VecDeque<Error>,
+    call_failures: Vec<(CallMatcher, Error)>,
+    calls: Vec<AtlasCall>,
+    next_id: u64,
+}
+
+impl FakeAtlasUserApi {
+    /// Adds a user to the organization and returns its ID
+    pub fn add_user(
+        &self,
+        org_id: &str,
+        username: &str,
+        membership_status: UserOrgMembershipStatus,
+        roles: AtlasUserRoles,
+    ) -> String {
+        let mut state = self.state.lock().unwrap();
+        let id = next_id(&mut state);
+        let user = user_response(&id, username, membership_status, roles, Vec::new());
+        state.users.push((org_id.to_string(), user));
+        id
+    }
+
+    /// Lets the pending invitation of the user expire
+    pub fn expire_invitation(&self, org_id: &str, user_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        let index = find(&state, org_id, user_id).expect("user to expire the invitation of");
+        let user = &mut state.users[index].1;
+        user.invitation_created_at = Some(Utc::now() - Duration::days(31));
+        user.invitation_expires_at = Some(Utc::now() - Duration::days(1));
+    }
+
+    /// The user as currently stored by the fake
+    pub fn user(&self, org_id: &str, user_id: &str) -> Option<UserResponse> {
+        let state = self.state.lock().unwrap();
+        find(&state, org_id, user_id).map(|index| state.users[index].1.clone())
+    }
+
+    /// Fails the next call with the given error
+    pub fn fail_next(&self, error: Error) {
+        self.state.lock().unwrap().failures.push_back(error);
+    }
+
+    /// Fails the next call the predicate matches with the given error
+    pub fn fail_next_call(&self, matches: CallMatcher, error: Error) {
+        self.state.lock().unwrap().call_failures.push((matches, error));
+    }
+
+    /// Every call made so far, in order
+    pub fn calls(&self) -> Vec<AtlasCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Records the call and returns the state, unless the call is to fail
+    fn call(&self, call: AtlasCall) -> Result<std::sync::MutexGuard<'_, FakeState>> {
+        let mut state = self.state.lock().unwrap();
+        let call_failure = state.call_failures.iter().position(|(matches, _)| matches(&call));
+        state.calls.push(call);
+        if let Some(index) = call_failure {
+            return Err(state.call_failures.remove(index).1);
+        }
+        match state.failures.pop_front() {
+            Some(error) => Err(error),
+            None => Ok(state),
+        }
+    }
+}
+
+#[async_trait]
+impl AtlasUserApi for FakeAtlasUserApi {
+    async fn invite_atlas_user(&self, org_id: &str, user: &UserRequest<'_>) -> Result<UserResponse> {
+        let username = user.username.expect("invite with username");
+        let mut state = self.call(AtlasCall::Invite {
+            org_id: org_id.to_string(),
+            username: username.to_string(),
+        })?;
+
+        let existing = state
+            .users
+            .iter()
+            .any(|(org, existing)| org == org_id && existing.username.eq_ignore_ascii_case(username));
+        if existing {
+            return Err(Error::UserAlreadyInOrg(Default::default()));
+        }
+
+        let id = next_id(&mut state);
+        let roles = user.roles.clone();
+        let mut invited = user_response(
+            &id,
+            username,
+            UserOrgMembershipStatus::Pending,
+            roles,
+            user.team_ids.to_vec(),
+        );
+        invited.invitation_created_at = Some(Utc::now());
+        invited.invitation_expires_at = Some(Utc::now() + Duration::days(30));
+        state.users.push((org_id.to_string(), invited.clone()));
+        Ok(invited)
+    }
+
+    async fn update_atlas_user(&self, org_id: &str, user_id: &str, user: &UserRequest<'_>) -> Result<UserResponse> {
+        let mut state = self.call(AtlasCall::Update {
+            org_id: org_id.to_string(),
+            user_id: user_id.to_string(),
+        })?;
+
+        let index = find(&state, org_id, user_id).ok_or_else(|| not_found(org_id, user_id))?;
+        let stored = &mut state.users[index].1;
+        stored.roles = user.roles.clone();
+        stored.team_ids = user.team_ids.to_vec();
+        Ok(stored.clone())
+    }
+
+    async fn delete_atlas_user_from_org(&self, org_id: &str, user_id: &str) -> Result<()> {
+        let mut state = self.call(AtlasCall::Delete {
+            org_id: org_id.to_string(),
+            user_id: user_id.to_string(),
+        })?;
+
+        let index = find(&state, org_id, user_id).ok_or_else(|| not_found(org_id, user_id))?;
+        state.users.remove(index);
+        Ok(())
+    }
+
+    async fn get_atlas_user(&self, org_id: &str, user_id: &str) -> Result<UserResponse> {
+        let state = self.call(AtlasCall::Get {
+            org_id: org_id.to_string(),
+            user_id: user_id.to_string(),
+        })?;
+
+        let index = find(&state, org_id, user_id).ok_or_else(|| not_found(org_id, user_id))?;
+        Ok(state.users[index].1.clone())
+    }
+
+    async fn find_atlas_user_by_username(&self, org_id: &str, username: &str) -> Result<Option<UserResponse>> {
+        let state = self.call(AtlasCall::FindByUsername {
+            org_id: org_id.to_string(),
+            username: username.to_string(),
+        })?;
+
+        let user = state
+            .users
+            .iter()
+            .find(|(org, user)| org == org_id && user.username.eq_ignore_ascii_case(username))
+            .map(|(_, user)| user.clone());
+        Ok(user)
+    }
+
+    async fn verify_credentials(&self) -> Result<()> {
+        self.call(AtlasCall::VerifyCredentials).map(drop)
+    }
+}
+
+fn next_id(state: &mut FakeState) -> String {
+    state.next_id += 1;
+    format!("{:024x}", state.next_id)
+}
+
+fn find(state: &FakeState, org_id: &str, user_id: &str) -> Option<usize> {
+    state
+        .users
+        .iter()
+        .position(|(org, user)| org == org_id && &*user.id == user_id)
+}
+
+fn user_response(
+    id: &str,
+    username: &str,
+    org_membership_status: UserOrgMembershipStatus,
+    roles: AtlasUserRoles,
+    team_ids: Vec<String>,
+) -> UserResponse {
+    UserResponse {
+        id: Arc::from(id),
+        org_membership_status,
+        roles,
+        team_ids,
+        username: username.to_string(),
+        invitation_created_at: None,
+        invitation_expires_at: None,
+        country: None,
+        first_name: None,
+        last_name: None,
+    }
+}
+
+fn not_found(org_id: &str, user_id: &str) -> Error {
+    Error::AtlasUserNotFound {
+        user_id: user_id.to_string(),
+        org_id: org_id.to_string(),
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use http::Method;
+use http::Request;
+use http::Response;
+use http::StatusCode;
+use k8s_openapi::api::coordination::v1::Lease;
+use k8s_openapi::api::core::v1::Event;
+use k8s_openapi::api::core::v1::Secret;
+use kube::client::Body;
+use kube::Client;
+use kuberator::cache::CachingStrategy;
+use kuberator::cache::StaticApiProvider;
+use kuberator::events::EventRecorder;
+use kuberator::k8s::K8sRepository;
+use mongodb_atlas_k8s_operator::crd::AtlasUser;
+use mongodb_atlas_k8s_operator::crd::AtlasUserStatus;
+use mongodb_atlas_k8s_operator::k8s::AtlasUserEventRecorder;
+use mongodb_atlas_k8s_operator::k8s::AtlasUserK8sRepo;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+pub const NAMESPACE: &str = "default";
+
+/// In-memory stand-in for the Kubernetes API server, serving the requests the operator makes while reconciling:
+/// AtlasUser status patches, Event creation, Secret reads and the Lease of the leader election
+#[derive(Clone, Default)]
+pub struct FakeKube {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    atlas_users: HashMap<String, Value>,
+    secrets: HashMap<String, Value>,
+    leases: HashMap<String, Value>,
+    /// Last resourceVersion handed out for a Lease
+    resource_version: u64,
+    events: Vec<Event>,
+}
+
+impl FakeKube {
+    /// Stores the AtlasUser, so that status patches can be applied to it
+    pub fn insert(&self, atlas_user: &AtlasUser) {
+        let name = atlas_user.metadata.name.clone().expect("AtlasUser without name");
+        let value = serde_json::to_value(atlas_user).expect("serializable AtlasUser");
+        self.state.lock().unwrap().atlas_users.insert(name, value);
+    }
+
+    pub fn insert_secret(&self, secret: &Secret) {
+        let name = secret.metadata.name.clone().expect("Secret without name");
+        let value = serde_json::to_value(secret).expect("serializable Secret");
+        self.state.lock().unwrap().secrets.insert(name, value);
+    }
+
+    /// Stores the Lease as if another replica had written it
+    pub fn insert_lease(&self, lease: &Lease) {
+        let name = lease.metadata.name.clone().expect("Lease without name");
+        let mut value = serde_json::to_value(lease).expect("serializable Lease");
+        let mut state = self.state.lock().unwrap();
+        state.stamp_resource_version(&mut value);
+        state.leases.insert(name, value);
+    }
+
+    /// The Lease last written
+    pub fn lease(&self, name: &str) -> Option<Lease> {
+        let state = self.state.lock().unwrap();
+        let lease = state.leases.get(name)?.clone();
+        Some(serde_json::from_value(lease).expect("valid Lease"))
+    }
+
+    /// The status last written to the AtlasUser
+    pub fn status(&self, name: &str) -> Option<AtlasUserStatus> {
+        let state = self.state.lock().unwrap();
+        let status = state.atlas_users.get(name)?.get("status")?.clone();
+        Some(serde_json::from_value(status).expect("valid AtlasUser status"))
+    }
+
+    /// Reasons of all Events created so far
+    pub fn event_reasons(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.events.iter().filter_map(|event| event.reason.clone()).collect()
+    }
+
+    pub fn k8s_repo(&self) -> Arc<AtlasUserK8sRepo> {
+        let provider = StaticApiProvider::new(self.client(), [NAMESPACE], CachingStrategy::Adhoc);
+        Arc::new(K8sRepository::new(provider))
+    }
+
+    pub fn event_recorder(&self) -> Arc<AtlasUserEventRecorder> {
+        let provider = StaticApiProvider::<Event>::new(self.client(), [NAMESPACE], CachingStrategy::Adhoc);
+        Arc::new(EventRecorder::new(Arc::new(provider), "mongodb-atlas-k8s-operator"))
+    }
+
+    pub fn secret_api_provider(&self) -> StaticApiProvider<Secret> {
+        StaticApiProvider::new(self.client(), [NAMESPACE], CachingStrategy::Adhoc)
+    }
+
+    pub fn client(&self) -> Client {
+        let fake = self.clone();
+        let service = tower::service_fn(move |request: Request<Body>| {
+            let fake = fake.clone();
+            async move { Ok::<_, Infallible>(fake.handle(request).await) }
+        });
+
+        Client::new(service, NAMESPACE)
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+        let body = request
+            .into_body()
+            .collect_bytes()
+            .await
+            .expect("readable request body");
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+        let mut state = self.state.lock().unwrap();
+        match (method, segments.as_slice()) {
+            (Method::PATCH, ["apis", "moertel.com", "v1", "namespaces", _, "atlasusers", name, "status"]) => {
+                let patch: Value = serde_json::from_slice(&body).expect("JSON merge patch");
+                match state.atlas_users.get_mut(*name) {
+                    Some(atlas_user) => {
+                        merge(atlas_user, &patch);
+                        respond(StatusCode::OK, atlas_user)
+                    }
+                    None => not_found(name),
+                }
+            }
+            (Method::POST, ["api", "v1", "namespaces", _, "events"]) => {
+                let event: Event = serde_json::from_slice(&body).expect("valid Event");
+                let response = serde_json::to_value(&event).expect("serializable Event");
+                state.events.push(event);
+                respond(StatusCode::CREATED, &response)
+            }
+            (Method::GET, ["api", "v1", "namespaces", _, "secrets", name]) => match state.secrets.get(*name) {
+                Some(secret) => respond(StatusCode::OK, secret),
+                None => not_found(name),
+            },
+            (Method::GET, ["apis", "coordination.k8s.io", "v1", "namespaces", _, "leases", name]) => {
+                match state.leases.get(*name) {
+                    Some(lease) => respond(StatusCode::OK, lease),
+                    None => not_found(name),
+                }
+            }
+            (Method::POST, ["apis", "coordination.k8s.io", "v1", "namespaces", _, "leases"]) => {
+                let mut lease: Value = serde_json::from_slice(&body).expect("valid Lease");
+                let name = lease["metadata"]["name"].as_str().expect("Lease with name").to_string();
+                if state.leases.contains_key(&name) {
+                    return conflict(&name);
+                }
+                state.stamp_resource_version(&mut lease);
+                state.leases.insert(name, lease.clone());
+                respond(StatusCode::CREATED, &lease)
+            }
+            (Method::PUT, ["apis", "coordination.k8s.io", "v1", "namespaces", _, "leases", name]) => {
+                let mut lease: Value = serde_json::from_slice(&body).expect("valid Lease");
+                let current_version = state
+                    .leases
+                    .get(*name)
+                    .map(|current| current["metadata"]["resourceVersion"].clone());
+                match current_version {
+                    None => not_found(name),
+                    Some(version) if version != lease["metadata"]["resourceVersion"] => conflict(name),
+                    Some(_) => {
+                        state.stamp_resource_version(&mut lease);
+                        state.leases.insert(name.to_string(), lease.clone());
+                        respond(StatusCode::OK, &lease)
+                    }
+                }
+            }
+            (method, _) => panic!("unexpected Kubernetes request {method} {path}"),
+        }
+    }
+}
+
+impl State {
+    /// Sets a new resourceVersion on the object, as the API server does on every write
+    fn stamp_resource_version(&mut self, object: &mut Value) {
+        self.resource_version += 1;
+        object["metadata"]["resourceVersion"] = Value::String(self.resource_version.to_string());
+    }
+}
+
+/// Applies a JSON merge patch (RFC 7386)
+fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let target = target.as_object_mut().expect("object after initialization");
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+fn respond(status: StatusCode, body: &Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_vec(body).expect("serializable response")))
+        .expect("valid response")
+}
+
+fn not_found(name: &str) -> Response<Body> {
+    let status = json!({
+        "kind": "Status",
+        "apiVersion": "v1",
+        "status": "Failure",
+        "message": format!("{name} not found"),
+        "reason": "NotFound",
+        "code": 404
+    });
+    respond(StatusCode::NOT_FOUND, &status)
+}
+
+fn conflict(name: &str) -> Response<Body> {
+    let status = json!({
+        "kind": "Status",
+        "apiVersion": "v1",
+        "status": "Failure",
+        "message": format!("Operation cannot be fulfilled on {name}: the object has been modified"),
+        "reason": "Conflict",
+        "code": 409
+    });
+    respond(StatusCode::CONFLICT, &status)
+}
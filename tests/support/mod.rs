@@ -0,0 +1,273 @@
+#![allow(dead_code)]
+
+pub mod atlas;
+pub mod auth;
+pub mod fake;
+pub mod kube;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ::kube::api::ObjectMeta;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use k8s_openapi::ByteString;
+use mongodb_atlas_k8s_operator::atlas::auth::Credentials;
+use mongodb_atlas_k8s_operator::atlas::AtlasClient;
+use mongodb_atlas_k8s_operator::atlas::AtlasConnections;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserApi;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserContext;
+use mongodb_atlas_k8s_operator::atlas::AtlasUserRepository;
+use mongodb_atlas_k8s_operator::config::AtlasApiConfig;
+use mongodb_atlas_k8s_operator::config::AtlasUserConfig;
+use mongodb_atlas_k8s_operator::config::DriftPolicy;
+use mongodb_atlas_k8s_operator::config::IdentityChangePolicy;
+use mongodb_atlas_k8s_operator::config::RetryConfig;
+use mongodb_atlas_k8s_operator::crd::AdoptionPolicy;
+use mongodb_atlas_k8s_operator::crd::AtlasUser;
+use mongodb_atlas_k8s_operator::crd::AtlasUserRoles;
+use mongodb_atlas_k8s_operator::crd::AtlasUserSpec;
+use mongodb_atlas_k8s_operator::crd::AtlasUserStatus;
+use mongodb_atlas_k8s_operator::crd::ConditionStatus;
+use mongodb_atlas_k8s_operator::crd::ConditionType;
+use mongodb_atlas_k8s_operator::crd::ExternalDeletionPolicy;
+use mongodb_atlas_k8s_operator::crd::GroupRoleAssignment;
+use mongodb_atlas_k8s_operator::crd::GroupRoleName;
+use mongodb_atlas_k8s_operator::crd::OrgRoleName;
+use mongodb_atlas_k8s_operator::index::AtlasUserIndex;
+use mongodb_atlas_k8s_operator::metrics::Metrics;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::support::atlas::MockAtlas;
+use crate::support::fake::FakeAtlasUserApi;
+use crate::support::kube::FakeKube;
+use crate::support::kube::NAMESPACE;
+
+pub const ORG_ID: &str = "5f1b2c3d4e5f6a7b8c9d0e1f";
+pub const GROUP_ID: &str = "6a7b8c9d0e1f5f1b2c3d4e5f";
+pub const USERNAME: &str = "john.doe@example.com";
+/// Creation time of the AtlasUser of the [`Harness`]
+pub const CREATED: &str = "2026-01-01T00:00:00Z";
+/// Requeue duration of [`config`]
+pub const REQUEUE: Duration = Duration::from_secs(60);
+
+/// Config that deletes users from Atlas and applies the default policies otherwise
+pub fn config() -> AtlasUserConfig {
+    AtlasUserConfig {
+        requeue_duration: REQUEUE,
+        safe_to_delete: true,
+        drift_policy: DriftPolicy::Correct,
+        identity_change_policy: IdentityChangePolicy::Reject,
+        max_reinvitations: 3,
+        on_external_deletion: ExternalDeletionPolicy::Fail,
+        adoption_policy: AdoptionPolicy::Adopt,
+    }
+}
+
+/// An AtlasUserContext wired to a fake Kubernetes API and either the mock Atlas API served over HTTP or an
+/// in-memory [`FakeAtlasUserApi`]
+pub struct Harness<A = MockAtlas> {
+    pub atlas: A,
+    pub kube: FakeKube,
+    pub index: Arc<AtlasUserIndex>,
+    pub metrics: Arc<Metrics>,
+    pub context: AtlasUserContext,
+}
+
+impl Harness {
+    pub async fn new(config: AtlasUserConfig) -> Self {
+        let (atlas, base_url) = MockAtlas::start().await;
+        let kube = FakeKube::default();
+
+        let api_config = AtlasApiConfig {
+            base_url,
+            retry: RetryConfig {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(10),
+            },
+            ..AtlasApiConfig::default()
+        };
+        let metrics = Arc::new(Metrics::new().expect("metrics registry"));
+        let atlas_client = Arc::new(AtlasClient::new(&api_config, Arc::clone(&metrics)).expect("Atlas client"));
+        let credentials = Credentials::AccessToken(Arc::from("test-token"));
+        let default_repo: Arc<dyn AtlasUserApi> =
+            Arc::new(AtlasUserRepository::new(credentials, Arc::clone(&atlas_client)));
+        let index = Arc::new(AtlasUserIndex::default());
+        let context = context(
+            &kube,
+            default_repo,
+            atlas_client,
+            Arc::clone(&metrics),
+            config,
+            Arc::clone(&index),
+        );
+
+        Harness {
+            atlas,
+            kube,
+            index,
+            metrics,
+            context,
+        }
+    }
+}
+
+impl Harness<Arc<FakeAtlasUserApi>> {
+    pub fn with_fake(config: AtlasUserConfig) -> Self {
+        let atlas = Arc::new(FakeAtlasUserApi::default());
+        let kube = FakeKube::default();
+
+        let metrics = Arc::new(Metrics::new().expect("metrics registry"));
+        let atlas_client =
+            Arc::new(AtlasClient::new(&AtlasApiConfig::default(), Arc::clone(&metrics)).expect("Atlas client"));
+        let default_repo: Arc<dyn AtlasUserApi> = atlas.clone();
+        let index = Arc::new(AtlasUserIndex::default());
+        let context = context(
+            &kube,
+            default_repo,
+            atlas_client,
+            Arc::clone(&metrics),
+            config,
+            Arc::clone(&index),
+        );
+
+        Harness {
+            atlas,
+            kube,
+            index,
+            metrics,
+            context,
+        }
+    }
+}
+
+impl<A> Harness<A> {
+    /// Stores the AtlasUser in the fake Kubernetes API and the index and returns it for reconciliation
+    pub fn atlas_user(&self, generation: i64, status: Option<AtlasUserStatus>) -> Arc<AtlasUser> {
+        self.atlas_user_with_spec(spec(), generation, status)
+    }
+
+    /// Like [`Harness::atlas_user`], with the given spec
+    pub fn atlas_user_with_spec(
+        &self,
+        spec: AtlasUserSpec,
+        generation: i64,
+        status: Option<AtlasUserStatus>,
+    ) -> Arc<AtlasUser> {
+        let mut atlas_user = atlas_user(NAMESPACE, "john-doe", CREATED);
+        atlas_user.spec = spec;
+        atlas_user.metadata.generation = Some(generation);
+        atlas_user.status = status;
+
+        self.kube.insert(&atlas_user);
+        self.index.apply(&atlas_user);
+        Arc::new(atlas_user)
+    }
+
+    /// The status last written to the AtlasUser
+    pub fn status(&self) -> AtlasUserStatus {
+        self.kube.status("john-doe").expect("status written")
+    }
+
+    /// Status and reason of the given condition in the status last written to the AtlasUser
+    pub fn condition(&self, type_: ConditionType) -> (ConditionStatus, String) {
+        let status = self.status();
+        let condition = status
+            .conditions
+            .iter()
+            .find(|condition| condition.type_ == type_)
+            .unwrap_or_else(|| panic!("condition {type_:?} set"));
+        (condition.status, condition.reason.clone())
+    }
+}
+
+fn context(
+    kube: &FakeKube,
+    default_repo: Arc<dyn AtlasUserApi>,
+    atlas_client: Arc<AtlasClient>,
+    metrics: Arc<Metrics>,
+    config: AtlasUserConfig,
+    index: Arc<AtlasUserIndex>,
+) -> AtlasUserContext {
+    let atlas_connections = Arc::new(AtlasConnections::new(
+        Some(default_repo),
+        kube.secret_api_provider(),
+        atlas_client,
+    ));
+
+    AtlasUserContext::new(
+        atlas_connections,
+        kube.k8s_repo(),
+        kube.event_recorder(),
+        metrics,
+        config,
+        index,
+    )
+}
+
+/// An AtlasUser with the spec of [`spec`], created at the given RFC 3339 time
+pub fn atlas_user(namespace: &str, name: &str, created: &str) -> AtlasUser {
+    let mut atlas_user = AtlasUser::new(name, spec());
+    atlas_user.metadata = ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: Some(namespace.to_string()),
+        creation_timestamp: Some(Time(created.parse().expect("RFC 3339 creation time"))),
+        ..ObjectMeta::default()
+    };
+    atlas_user
+}
+
+pub fn spec() -> AtlasUserSpec {
+    AtlasUserSpec {
+        org_id: ORG_ID.to_string(),
+        username: USERNAME.to_string(),
+        roles: AtlasUserRoles {
+            group_role_assignments: vec![GroupRoleAssignment {
+                group_id: GROUP_ID.to_string(),
+                group_roles: vec![GroupRoleName::GroupReadOnly],
+            }],
+            org_roles: vec![OrgRoleName::OrgMember],
+        },
+        team_ids: Vec::new(),
+        connection_secret_ref: None,
+        on_external_deletion: None,
+        deletion_policy: None,
+        adoption_policy: None,
+    }
+}
+
+/// The roles of [`spec`] as returned by Atlas
+pub fn spec_roles() -> Value {
+    json!({
+        "orgRoles": ["ORG_MEMBER"],
+        "groupRoleAssignments": [{ "groupId": GROUP_ID, "groupRoles": ["GROUP_READ_ONLY"] }],
+    })
+}
+
+/// A connection Secret with the given access token
+pub fn connection_secret(name: &str, access_token: &str) -> Secret {
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(NAMESPACE.to_string()),
+            ..ObjectMeta::default()
+        },
+        data: Some(BTreeMap::from([(
+            "accessToken".to_string(),
+            ByteString(access_token.as_bytes().to_vec()),
+        )])),
+        ..Secret::default()
+    }
+}
+
+/// Status of a user that was invited and reconciled at the given generation
+pub fn reconciled_status(user_id: &str, observed_generation: i64) -> AtlasUserStatus {
+    AtlasUserStatus {
+        user_id: Some(Arc::from(user_id)),
+        observed_generation: Some(observed_generation),
+        ..AtlasUserStatus::default()
+    }
+}
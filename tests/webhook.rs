@@ -0,0 +1,345 @@
+mod support;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use http::Request;
+use mongodb_atlas_k8s_operator::config::IdentityChangePolicy;
+use mongodb_atlas_k8s_operator::crd::AtlasUserSpec;
+use mongodb_atlas_k8s_operator::crd::GroupRoleAssignment;
+use mongodb_atlas_k8s_operator::crd::GroupRoleName;
+use mongodb_atlas_k8s_operator::index::AtlasUserIndex;
+use mongodb_atlas_k8s_operator::webhook;
+use mongodb_atlas_k8s_operator::webhook::validation::validate;
+use mongodb_atlas_k8s_operator::webhook::validation::validate_update;
+use mongodb_atlas_k8s_operator::webhook::WebhookService;
+use mongodb_atlas_k8s_operator::webhook::VALIDATE_PATH;
+use serde_json::json;
+use serde_json::Value;
+use tower::ServiceExt;
+
+use crate::support::spec;
+use crate::support::GROUP_ID;
+use crate::support::ORG_ID;
+use crate::support::USERNAME;
+
+#[test]
+fn valid_spec_is_accepted() {
+    assert_eq!(validate(&spec()), Vec::<String>::new());
+}
+
+#[test]
+fn invalid_username_is_rejected() {
+    for username in [
+        "john.doe",
+        "@example.com",
+        "john@example",
+        "john doe@example.com",
+        "john@@example.com",
+    ] {
+        let spec = AtlasUserSpec {
+            username: username.to_string(),
+            ..spec()
+        };
+
+        assert_eq!(
+            validate(&spec),
+            [format!("spec.username: {username:?} is not a valid email address")],
+            "{username}"
+        );
+    }
+}
+
+#[test]
+fn malformed_ids_are_rejected() {
+    let mut spec = spec();
+    spec.org_id = "not-an-org".to_string();
+    spec.roles.group_role_assignments[0].group_id = format!("{GROUP_ID}0");
+
+    assert_eq!(
+        validate(&spec),
+        [
+            "spec.orgId: \"not-an-org\" is not a 24-character hexadecimal organization ID".to_string(),
+            format!(
+                "spec.roles.groupRoleAssignments[0].groupId: \"{GROUP_ID}0\" is not a 24-character hexadecimal \
+                group (project) ID"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn empty_group_roles_are_rejected() {
+    let mut spec = spec();
+    spec.roles.group_role_assignments[0].group_roles.clear();
+
+    assert_eq!(
+        validate(&spec),
+        ["spec.roles.groupRoleAssignments[0].groupRoles: must contain at least one role"]
+    );
+}
+
+#[test]
+fn duplicate_group_ids_are_rejected() {
+    let mut spec = spec();
+    spec.roles.group_role_assignments.push(GroupRoleAssignment {
+        group_id: GROUP_ID.to_uppercase(),
+        group_roles: vec![GroupRoleName::GroupOwner],
+    });
+
+    assert_eq!(
+        validate(&spec),
+        [format!(
+            "spec.roles.groupRoleAssignments[1].groupId: group \"{}\" is assigned more than once, merge its roles \
+            into one assignment",
+            GROUP_ID.to_uppercase()
+        )]
+    );
+}
+
+#[test]
+fn username_and_org_are_immutable() {
+    let mut new = spec();
+    new.username = "jane.doe@example.com".to_string();
+    new.org_id = "0123456789abcdef01234567".to_string();
+
+    assert_eq!(
+        validate_update(&spec(), &new),
+        [
+            "spec.username: is immutable, cannot change \"john.doe@example.com\" to \"jane.doe@example.com\"",
+            &format!("spec.orgId: is immutable, cannot change \"{ORG_ID}\" to \"0123456789abcdef01234567\""),
+        ]
+    );
+}
+
+#[test]
+fn username_case_may_change() {
+    let mut new = spec();
+    new.username = new.username.to_uppercase();
+
+    assert_eq!(validate_update(&spec(), &new), Vec::<String>::new());
+}
+
+fn atlas_user(spec: Value) -> Value {
+    json!({
+        "apiVersion": "moertel.com/v1",
+        "kind": "AtlasUser",
+        "metadata": { "name": "john-doe", "namespace": "default" },
+        "spec": spec,
+    })
+}
+
+fn admission_review(operation: &str, object: Value, old_object: Option<Value>) -> Value {
+    json!({
+        "apiVersion": "admission.k8s.io/v1",
+        "kind": "AdmissionReview",
+        "request": {
+            "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+            "kind": { "group": "moertel.com", "version": "v1", "kind": "AtlasUser" },
+            "resource": { "group": "moertel.com", "version": "v1", "resource": "atlasusers" },
+            "name": "john-doe",
+            "namespace": "default",
+            "operation": operation,
+            "userInfo": { "username": "admin" },
+            "object": object,
+            "oldObject": old_object,
+            "dryRun": false,
+        },
+    })
+}
+
+/// Sends the AdmissionReview to the webhook and returns the response of the review
+async fn review(review: Value) -> Value {
+    review_with_index(review, AtlasUserIndex::default()).await
+}
+
+async fn review_with_index(review: Value, index: AtlasUserIndex) -> Value {
+    review_with(review, index, IdentityChangePolicy::Reject).await
+}
+
+async fn review_with(review: Value, index: AtlasUserIndex, identity_change_policy: IdentityChangePolicy) -> Value {
+    let request = Request::post(VALIDATE_PATH)
+        .header("content-type", "application/json")
+        .body(Body::from(review.to_string()))
+        .unwrap();
+
+    let response = webhook::router(Arc::new(index), identity_change_policy)
+        .oneshot(request)
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let review: Value = serde_json::from_slice(&body).unwrap();
+    review["response"].clone()
+}
+
+fn spec_json() -> Value {
+    serde_json::to_value(spec()).unwrap()
+}
+
+#[tokio::test]
+async fn webhook_allows_valid_create() {
+    let response = review(admission_review("CREATE", atlas_user(spec_json()), None)).await;
+
+    assert_eq!(response["uid"], "705ab4f5-6393-11e8-b7cc-42010a800002");
+    assert_eq!(response["allowed"], true);
+}
+
+#[tokio::test]
+async fn webhook_denies_invalid_create_with_all_violations() {
+    let mut spec = spec_json();
+    spec["username"] = json!("john.doe");
+    spec["orgId"] = json!("org");
+
+    let response = review(admission_review("CREATE", atlas_user(spec), None)).await;
+
+    assert_eq!(response["allowed"], false);
+    assert_eq!(
+        response["status"]["message"],
+        "spec.username: \"john.doe\" is not a valid email address; \
+        spec.orgId: \"org\" is not a 24-character hexadecimal organization ID"
+    );
+}
+
+#[tokio::test]
+async fn webhook_denies_changing_username() {
+    let mut spec = spec_json();
+    spec["username"] = json!("jane.doe@example.com");
+
+    let response = review(admission_review(
+        "UPDATE",
+        atlas_user(spec),
+        Some(atlas_user(spec_json())),
+    ))
+    .await;
+
+    assert_eq!(response["allowed"], false);
+    assert_eq!(
+        response["status"]["message"],
+        "spec.username: is immutable, cannot change \"john.doe@example.com\" to \"jane.doe@example.com\""
+    );
+}
+
+#[tokio::test]
+async fn webhook_allows_update_leaving_invalid_spec_unchanged() {
+    let mut spec = spec_json();
+    spec["username"] = json!("john.doe");
+    let mut finalized = atlas_user(spec.clone());
+    finalized["metadata"]["finalizers"] = json!(["atlasusers.moertel.com/finalizer"]);
+
+    let response = review(admission_review("UPDATE", finalized, Some(atlas_user(spec)))).await;
+
+    assert_eq!(response["allowed"], true);
+}
+
+#[tokio::test]
+async fn webhook_denies_duplicate_create() {
+    let index = AtlasUserIndex::default();
+    index.apply(&support::atlas_user("team-a", "john", "2025-12-01T00:00:00Z"));
+    let mut spec = spec_json();
+    spec["username"] = json!(USERNAME.to_uppercase());
+
+    let response = review_with_index(admission_review("CREATE", atlas_user(spec), None), index).await;
+
+    assert_eq!(response["allowed"], false);
+    assert_eq!(
+        response["status"]["message"],
+        format!(
+            "spec.username: user \"{}\" in organization \"{ORG_ID}\" is already managed by AtlasUser team-a/john",
+            USERNAME.to_uppercase()
+        )
+    );
+}
+
+#[tokio::test]
+async fn webhook_allows_update_of_indexed_user() {
+    let index = AtlasUserIndex::default();
+    index.apply(&support::atlas_user("default", "john-doe", "2025-12-01T00:00:00Z"));
+    let mut spec = spec_json();
+    spec["teamIds"] = json!(["0123456789abcdef01234567"]);
+
+    let review = admission_review("UPDATE", atlas_user(spec), Some(atlas_user(spec_json())));
+    let response = review_with_index(review, index).await;
+
+    assert_eq!(response["allowed"], true);
+}
+
+#[tokio::test]
+async fn webhook_allows_changing_username_when_migrating() {
+    let mut spec = spec_json();
+    spec["username"] = json!("jane.doe@example.com");
+
+    let review = admission_review("UPDATE", atlas_user(spec), Some(atlas_user(spec_json())));
+    let response = review_with(review, AtlasUserIndex::default(), IdentityChangePolicy::Migrate).await;
+
+    assert_eq!(response["allowed"], true);
+}
+
+#[tokio::test]
+async fn webhook_denies_migrating_to_managed_user() {
+    let index = AtlasUserIndex::default();
+    let mut other = support::atlas_user("team-a", "jane", "2025-12-01T00:00:00Z");
+    other.spec.username = "jane.doe@example.com".to_string();
+    index.apply(&other);
+    let mut spec = spec_json();
+    spec["username"] = json!("jane.doe@example.com");
+
+    let review = admission_review("UPDATE", atlas_user(spec), Some(atlas_user(spec_json())));
+    let response = review_with(review, index, IdentityChangePolicy::Migrate).await;
+
+    assert_eq!(response["allowed"], false);
+    assert_eq!(
+        response["status"]["message"],
+        format!(
+            "spec.username: user \"jane.doe@example.com\" in organization \"{ORG_ID}\" is already managed by AtlasUser team-a/jane"
+        )
+    );
+}
+
+#[tokio::test]
+async fn webhook_denies_invalid_deletion_policy_annotation() {
+    let mut object = atlas_user(spec_json());
+    object["metadata"]["annotations"] = json!({ "atlasusers.moertel.com/deletion-policy": "Retian" });
+
+    let response = review(admission_review("UPDATE", object, Some(atlas_user(spec_json())))).await;
+
+    assert_eq!(response["allowed"], false);
+    assert_eq!(
+        response["status"]["message"],
+        "metadata.annotations[atlasusers.moertel.com/deletion-policy]: \"Retian\" is not one of Delete, Retain, \
+        RemoveProjectRolesOnly"
+    );
+}
+
+#[tokio::test]
+async fn webhook_allows_valid_deletion_policy_annotation() {
+    let mut object = atlas_user(spec_json());
+    object["metadata"]["annotations"] = json!({ "atlasusers.moertel.com/deletion-policy": "RemoveProjectRolesOnly" });
+
+    let response = review(admission_review("CREATE", object, None)).await;
+
+    assert_eq!(response["allowed"], true);
+}
+
+#[test]
+fn configuration_points_at_service() {
+    let service = WebhookService {
+        name: "operator".to_string(),
+        namespace: "atlas".to_string(),
+        port: 8443,
+        ca_bundle: Some(b"-----BEGIN CERTIFICATE-----".to_vec()),
+    };
+
+    let configuration = serde_json::to_value(webhook::configuration(&service)).unwrap();
+
+    let webhook = &configuration["webhooks"][0];
+    assert_eq!(
+        webhook["clientConfig"],
+        json!({
+            "caBundle": "LS0tLS1CRUdJTiBDRVJUSUZJQ0FURS0tLS0t",
+            "service": { "name": "operator", "namespace": "atlas", "path": VALIDATE_PATH, "port": 8443 },
+        })
+    );
+    assert_eq!(webhook["rules"][0]["resources"], json!(["atlasusers"]));
+    assert_eq!(webhook["rules"][0]["operations"], json!(["CREATE", "UPDATE"]));
+}